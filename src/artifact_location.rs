@@ -51,7 +51,8 @@ pub fn determine_location(
     artifact_entry: &ArtifactEntry,
     dotslash_cache: &DotslashCache,
 ) -> ArtifactLocation {
-    let artifact_hash = blake3::Hasher::new()
+    let mut hasher = blake3::Hasher::new();
+    hasher
         .update(artifact_entry.size.to_string().as_bytes())
         .update(b"\0")
         .update(create_key_for_hash_algorithm(&artifact_entry.hash))
@@ -60,8 +61,30 @@ pub fn determine_location(
         .update(b"\0")
         .update(create_key_for_format(artifact_entry).as_bytes())
         .update(b"\0")
-        .update(if artifact_entry.readonly { b"1" } else { b"0" })
-        .finalize();
+        .update(if artifact_entry.readonly { b"1" } else { b"0" });
+    // Only added when present so that existing cache keys are unchanged.
+    if let Some(dictionary) = artifact_entry
+        .zstd
+        .as_ref()
+        .and_then(|zstd| zstd.dictionary.as_ref())
+    {
+        hasher
+            .update(b"\0zstd-dictionary:")
+            .update(dictionary.digest.as_str().as_bytes());
+    }
+    for executable in &artifact_entry.executables {
+        hasher
+            .update(b"\0executable:")
+            .update(executable.as_bytes());
+    }
+    for (link, target) in &artifact_entry.symlinks {
+        hasher
+            .update(b"\0symlink:")
+            .update(link.as_str().as_bytes())
+            .update(b"\0")
+            .update(target.as_bytes());
+    }
+    let artifact_hash = hasher.finalize();
     let artifact_key = artifact_hash.as_bytes()[..NUM_HASH_BYTES_FOR_PATH]
         .iter()
         .fold(
@@ -97,16 +120,21 @@ fn create_key_for_hash_algorithm(hash: &HashAlgorithm) -> &'static [u8] {
 }
 
 fn create_key_for_format(entry: &ArtifactEntry) -> Cow<'_, str> {
-    match entry.format.extraction_policy() {
+    let key = match entry.format.extraction_policy() {
         (decompress, Some(ArchiveFormat::Tar)) => {
             // For an artifact that is an archive, the type of archive is
             // sufficient to distinguish it.
             match decompress {
                 None => Cow::Borrowed("tar"),
+                Some(DecompressStep::Bzip2) => Cow::Borrowed("tar.bz2"),
                 Some(DecompressStep::Gzip) => Cow::Borrowed("tar.gz"),
+                Some(DecompressStep::Xz) => Cow::Borrowed("tar.xz"),
                 Some(DecompressStep::Zstd) => Cow::Borrowed("tar.zst"),
             }
         }
+        (_, Some(ArchiveFormat::Deb)) => Cow::Borrowed("deb"),
+        (_, Some(ArchiveFormat::Rpm)) => Cow::Borrowed("rpm"),
+        (_, Some(ArchiveFormat::Zip)) => Cow::Borrowed("zip"),
         (decompress, None) => {
             // For a non-archive artifact, the `path` must be part of the cache
             // key. The key has a prefix to distinguish it from the cache keys
@@ -114,15 +142,31 @@ fn create_key_for_format(entry: &ArtifactEntry) -> Cow<'_, str> {
             let path = &entry.path;
             match decompress {
                 None => Cow::Owned(format!("file:{}", path)),
+                Some(DecompressStep::Bzip2) => Cow::Owned(format!("file.bz2:{}", path)),
                 Some(DecompressStep::Gzip) => Cow::Owned(format!("file.gz:{}", path)),
+                Some(DecompressStep::Xz) => Cow::Owned(format!("file.xz:{}", path)),
                 Some(DecompressStep::Zstd) => Cow::Owned(format!("file.zst:{}", path)),
             }
         }
+    };
+    // Which entries of an archive are unpacked, and where, changes the
+    // contents of the artifact directory. This is only added when specified
+    // so that existing cache keys are unchanged.
+    if entry.extract.is_empty() && entry.strip_components == 0 {
+        key
+    } else {
+        Cow::Owned(format!(
+            "{}\0extract:{}\0strip_components:{}",
+            key,
+            entry.extract.join("\0"),
+            entry.strip_components,
+        ))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
     use std::str::FromStr;
 
     use super::*;
@@ -143,6 +187,12 @@ mod tests {
             path: ArtifactPath::from_str("bin/sapling").unwrap(),
             providers: vec![],
             readonly: true,
+            zstd: None,
+            extract: vec![],
+            strip_components: 0,
+            executables: vec![],
+            symlinks: BTreeMap::new(),
+            interpreter: None,
         };
         let dotslash_cache = DotslashCache::default();
         let location = determine_location(&artifact_entry, &dotslash_cache);
@@ -183,6 +233,12 @@ mod tests {
             path: ArtifactPath::from_str("minesweeper.exe").unwrap(),
             providers: vec![],
             readonly: true,
+            zstd: None,
+            extract: vec![],
+            strip_components: 0,
+            executables: vec![],
+            symlinks: BTreeMap::new(),
+            interpreter: None,
         };
         let dotslash_cache = DotslashCache::default();
         let location = determine_location(&artifact_entry, &dotslash_cache);
@@ -209,4 +265,49 @@ mod tests {
                 .join("fd21d5ac7f30378d523758d64d902698559d72")
         );
     }
+
+    #[test]
+    fn extract_and_strip_components_change_the_key() {
+        let artifact_entry = |extract: &[&str], strip_components| ArtifactEntry {
+            size: 8675309,
+            hash: HashAlgorithm::Blake3,
+            digest: Digest::try_from(
+                "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069".to_owned(),
+            )
+            .unwrap(),
+            format: ArtifactFormat::TarGz,
+            path: ArtifactPath::from_str("bin/sapling").unwrap(),
+            providers: vec![],
+            readonly: true,
+            zstd: None,
+            extract: extract.iter().map(|glob| glob.to_string()).collect(),
+            strip_components,
+            executables: vec![],
+            symlinks: BTreeMap::new(),
+            interpreter: None,
+        };
+        let dotslash_cache = DotslashCache::default();
+        let directory = |entry| determine_location(&entry, &dotslash_cache).artifact_directory;
+
+        // Unchanged from `paths_for_extract_case`.
+        assert_eq!(
+            directory(artifact_entry(&[], 0)),
+            dotslash_cache
+                .artifacts_dir()
+                .join("0c")
+                .join("7cc25be015e0ab6855aaa7bfea49d5dffe5e4c")
+        );
+        let directories = [
+            directory(artifact_entry(&[], 0)),
+            directory(artifact_entry(&[], 1)),
+            directory(artifact_entry(&["bin/*"], 0)),
+            directory(artifact_entry(&["bin/*"], 1)),
+            directory(artifact_entry(&["bin/*", "lib/*"], 1)),
+        ];
+        for (i, a) in directories.iter().enumerate() {
+            for b in &directories[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
 }
@@ -19,7 +19,7 @@ use thiserror::Error;
 /// `ArtifactPath` is a newtype type for `String` rather than `PathBuf` because
 /// we want it to be unambiguously represented with forward slashes on all
 /// platforms.
-#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Clone)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Clone)]
 #[serde(try_from = "String")]
 pub struct ArtifactPath(String);
 
@@ -0,0 +1,101 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::path::Path;
+
+use anyhow::format_err;
+use serde::Deserialize;
+use serde_jsonrc::value::Value;
+
+use crate::config::ArtifactEntry;
+use crate::config::HashAlgorithm;
+use crate::http_provider::fetch_url;
+use crate::provider::Provider;
+use crate::util::file_lock::FileLock;
+
+/// Fetches the artifact from a content-addressed cache, such as bazel-remote
+/// or Buildbarn, that serves blobs over HTTP at `{url}/cas/{sha256}`. Because
+/// the blob is keyed by the `digest` of the `ArtifactEntry`, the provider
+/// config only needs the base URL of the cache.
+pub struct CasProvider {}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct CasProviderConfig {
+    url: String,
+}
+
+impl Provider for CasProvider {
+    fn fetch_artifact(
+        &self,
+        provider_config: &Value,
+        destination: &Path,
+        _fetch_lock: &FileLock,
+        artifact_entry: &ArtifactEntry,
+        _dotslash_file: &Path,
+    ) -> anyhow::Result<()> {
+        let CasProviderConfig { url } = CasProviderConfig::deserialize(provider_config)?;
+        let blob_url = blob_url(&url, artifact_entry)?;
+        fetch_url(&blob_url, destination, artifact_entry)
+    }
+}
+
+fn blob_url(base_url: &str, artifact_entry: &ArtifactEntry) -> anyhow::Result<String> {
+    match artifact_entry.hash {
+        HashAlgorithm::Sha256 => Ok(format!(
+            "{}/cas/{}",
+            base_url.trim_end_matches('/'),
+            artifact_entry.digest,
+        )),
+        // The HTTP interface for these caches is keyed by SHA-256.
+        HashAlgorithm::Blake3 => Err(format_err!(
+            "the `cas` provider requires `\"hash\": \"sha256\"`"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artifact_entry(hash: &str) -> ArtifactEntry {
+        serde_jsonrc::from_value(serde_jsonrc::json!({
+            "size": 123,
+            "hash": hash,
+            "digest": "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069",
+            "format": "tar.gz",
+            "path": "bin/my_tool",
+            "providers": [],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn blob_url_for_sha256() {
+        let entry = artifact_entry("sha256");
+        let expected = "https://cache.example.com:8080/cas/7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069";
+        assert_eq!(
+            blob_url("https://cache.example.com:8080", &entry).unwrap(),
+            expected,
+        );
+        assert_eq!(
+            blob_url("https://cache.example.com:8080/", &entry).unwrap(),
+            expected,
+        );
+    }
+
+    #[test]
+    fn blob_url_for_blake3() {
+        assert_eq!(
+            blob_url("https://cache.example.com:8080", &artifact_entry("blake3"),)
+                .unwrap_err()
+                .to_string(),
+            "the `cas` provider requires `\"hash\": \"sha256\"`",
+        );
+    }
+}
@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 
 use anyhow::format_err;
@@ -16,8 +17,10 @@ use serde::Serialize;
 use serde_jsonrc::value::Value;
 
 use crate::artifact_path::ArtifactPath;
+use crate::decompress::EntryFilter;
 use crate::digest::Digest;
 use crate::fetch_method::ArtifactFormat;
+use crate::fetch_method::DecompressStep;
 
 /// A DotSlash file must start with exactly these bytes on the first line
 /// to be considered valid. Because a DotSlash file does not have a
@@ -28,7 +31,66 @@ pub const REQUIRED_HEADER: &str = "#!/usr/bin/env dotslash";
 #[derive(Deserialize, Debug, PartialEq)]
 pub struct ConfigFile {
     pub name: String,
-    pub platforms: HashMap<String, ArtifactEntry>,
+    /// Entries are only parsed on demand so that an entry that this version
+    /// of DotSlash does not understand (e.g., one with a newer `format`) does
+    /// not break the other platforms.
+    pub platforms: HashMap<String, Value>,
+}
+
+impl ConfigFile {
+    /// Parses the entry for `platform`, if there is one.
+    pub fn artifact_entry(&self, platform: &str) -> anyhow::Result<Option<ArtifactEntry>> {
+        self.platforms
+            .get(platform)
+            .map(|entry| {
+                ArtifactEntry::deserialize(entry)
+                    .map_err(anyhow::Error::from)
+                    .and_then(check_artifact_entry)
+                    .with_context(|| format!("invalid entry for platform `{}`", platform))
+            })
+            .transpose()
+    }
+
+    /// Parses the entries for all platforms and reports every invalid one.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut platforms = self.platforms.keys().collect::<Vec<_>>();
+        platforms.sort();
+        let errors = platforms
+            .into_iter()
+            .filter_map(|platform| self.artifact_entry(platform).err())
+            .map(|err| format!("{:#}", err))
+            .collect::<Vec<_>>();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format_err!("{}", errors.join("\n")))
+        }
+    }
+}
+
+/// Checks the fields of an entry that depend on one another.
+fn check_artifact_entry(entry: ArtifactEntry) -> anyhow::Result<ArtifactEntry> {
+    let (decompress, archive) = entry.format.extraction_policy();
+    if entry.zstd.is_some() && !matches!(decompress, Some(DecompressStep::Zstd)) {
+        return Err(format_err!(
+            "`zstd` may only be specified for the `zst` and `tar.zst` formats"
+        ));
+    }
+    if (!entry.extract.is_empty() || entry.strip_components != 0) && archive.is_none() {
+        return Err(format_err!(
+            "`extract` and `strip_components` may only be specified for archive formats"
+        ));
+    }
+    EntryFilter::new(&entry.extract, entry.strip_components)?;
+    entry.executable_patterns()?;
+    if entry
+        .interpreter
+        .as_ref()
+        .is_some_and(|interpreter| interpreter.argv().first().map_or(true, String::is_empty))
+    {
+        return Err(format_err!("`interpreter` must name a program"));
+    }
+    Ok(entry)
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq)]
@@ -42,6 +104,75 @@ pub struct ArtifactEntry<Format = ArtifactFormat> {
     pub providers: Vec<Value>,
     #[serde(default = "readonly_default_as_true", skip_serializing_if = "is_true")]
     pub readonly: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zstd: Option<ZstdOptions>,
+    /// Globs for the archive entries to unpack, matched after
+    /// `strip_components` is applied. All entries are unpacked if empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extract: Vec<String>,
+    /// The number of leading path components to remove from each entry.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub strip_components: usize,
+    /// Globs for the files in the artifact directory to make executable.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub executables: Vec<String>,
+    /// Symlinks to create in the artifact directory, from the path of each
+    /// symlink to its target.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub symlinks: BTreeMap<ArtifactPath, String>,
+    /// A program that runs the artifact, which is passed the path to the
+    /// artifact followed by the arguments to the DotSlash file.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interpreter: Option<Interpreter>,
+}
+
+impl<Format> ArtifactEntry<Format> {
+    pub fn executable_patterns(&self) -> anyhow::Result<Vec<glob::Pattern>> {
+        self.executables
+            .iter()
+            .map(|pattern| {
+                glob::Pattern::new(pattern)
+                    .with_context(|| format!("invalid `executables` glob `{}`", pattern))
+            })
+            .collect()
+    }
+}
+
+/// Either the path or name of a program, or an argv prefix such as
+/// `["java", "-jar"]`.
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum Interpreter {
+    Program(String),
+    Argv(Vec<String>),
+}
+
+impl Interpreter {
+    pub fn argv(&self) -> &[String] {
+        match self {
+            Interpreter::Program(program) => std::slice::from_ref(program),
+            Interpreter::Argv(argv) => argv,
+        }
+    }
+}
+
+/// Decoder parameters for the `zst` and `tar.zst` formats.
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct ZstdOptions {
+    /// Base-2 log of the largest window the decoder accepts. Frames created
+    /// with `zstd --long=N` need at least `N` if `N` is greater than 27.
+    pub window_log_max: Option<u32>,
+    /// The dictionary the artifact was compressed with, which is fetched
+    /// and verified like the artifact itself.
+    pub dictionary: Option<ZstdDictionary>,
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq)]
+pub struct ZstdDictionary {
+    pub size: u64,
+    pub hash: HashAlgorithm,
+    pub digest: Digest,
+    pub providers: Vec<Value>,
 }
 
 /// While having a boolean that defaults to `true` is somewhat undesirable,
@@ -55,7 +186,11 @@ fn is_true(b: &bool) -> bool {
     *b
 }
 
-#[derive(Deserialize, Serialize, Debug, PartialEq, Eq)]
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy)]
 pub enum HashAlgorithm {
     #[serde(rename = "blake3")]
     Blake3,
@@ -113,31 +248,30 @@ mod tests {
         }
         "#;
         let config_file = parse_file_string(dotslash).unwrap();
+        assert_eq!(config_file.name, "my_tool");
         assert_eq!(
-            config_file,
-            ConfigFile {
-                name: "my_tool".to_owned(),
-                platforms: [(
-                    "linux-x86_64".to_owned(),
-                    ArtifactEntry {
-                        size: 123,
-                        hash: HashAlgorithm::Sha256,
-                        digest: Digest::try_from(
-                            "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069"
-                                .to_owned(),
-                        )
-                        .unwrap(),
-                        format: ArtifactFormat::Tar,
-                        path: ArtifactPath::from_str("bindir/my_tool").unwrap(),
-                        providers: vec![serde_jsonrc::json!({
-                            "type": "http",
-                            "url": "https://example.com/my_tool.tar",
-                        })],
-                        readonly: true,
-                    }
-                )]
-                .into(),
-            },
+            config_file.artifact_entry("linux-x86_64").unwrap(),
+            Some(ArtifactEntry {
+                size: 123,
+                hash: HashAlgorithm::Sha256,
+                digest: Digest::try_from(
+                    "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069".to_owned(),
+                )
+                .unwrap(),
+                format: ArtifactFormat::Tar,
+                path: ArtifactPath::from_str("bindir/my_tool").unwrap(),
+                providers: vec![serde_jsonrc::json!({
+                    "type": "http",
+                    "url": "https://example.com/my_tool.tar",
+                })],
+                readonly: true,
+                zstd: None,
+                extract: vec![],
+                strip_components: 0,
+                executables: vec![],
+                symlinks: BTreeMap::new(),
+                interpreter: None,
+            }),
         );
     }
 
@@ -163,31 +297,288 @@ mod tests {
         }
         "#;
         let config_file = parse_file_string(dotslash).unwrap();
+        assert_eq!(config_file.name, "minesweeper");
         assert_eq!(
-            config_file,
-            ConfigFile {
-                name: "minesweeper".to_owned(),
-                platforms: [(
-                    "linux-x86_64".to_owned(),
-                    ArtifactEntry {
-                        size: 123,
-                        hash: HashAlgorithm::Sha256,
-                        digest: Digest::try_from(
-                            "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069"
-                                .to_owned(),
-                        )
-                        .unwrap(),
-                        format: ArtifactFormat::Plain,
-                        path: ArtifactPath::from_str("minesweeper.exe").unwrap(),
-                        providers: vec![serde_jsonrc::json!({
-                            "type": "http",
-                            "url": "https://foo.com",
-                        })],
-                        readonly: true,
-                    }
-                )]
-                .into(),
-            }
+            config_file.artifact_entry("linux-x86_64").unwrap(),
+            Some(ArtifactEntry {
+                size: 123,
+                hash: HashAlgorithm::Sha256,
+                digest: Digest::try_from(
+                    "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069".to_owned(),
+                )
+                .unwrap(),
+                format: ArtifactFormat::Plain,
+                path: ArtifactPath::from_str("minesweeper.exe").unwrap(),
+                providers: vec![serde_jsonrc::json!({
+                    "type": "http",
+                    "url": "https://foo.com",
+                })],
+                readonly: true,
+                zstd: None,
+                extract: vec![],
+                strip_components: 0,
+                executables: vec![],
+                symlinks: BTreeMap::new(),
+                interpreter: None,
+            }),
+        );
+    }
+
+    #[test]
+    fn invalid_entry_only_affects_its_platform() {
+        let dotslash = r#"#!/usr/bin/env dotslash
+        {
+            "name": "my_tool",
+            "platforms": {
+                "linux-x86_64": {
+                    "size": 123,
+                    "hash": "sha256",
+                    "digest": "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069",
+                    "path": "my_tool",
+                    "providers": [],
+                },
+                "linux-riscv64": {
+                    "size": 123,
+                    "hash": "sha256",
+                    "digest": "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069",
+                    "format": "from-the-future",
+                    "path": "my_tool",
+                    "providers": [],
+                },
+            },
+        }
+        "#;
+        let config_file = parse_file_string(dotslash).unwrap();
+        assert!(config_file
+            .artifact_entry("linux-x86_64")
+            .unwrap()
+            .is_some());
+        assert!(config_file
+            .artifact_entry("macos-x86_64")
+            .unwrap()
+            .is_none());
+        assert!(config_file
+            .artifact_entry("linux-riscv64")
+            .unwrap_err()
+            .to_string()
+            .starts_with("invalid entry for platform `linux-riscv64`"));
+        assert!(config_file.validate().unwrap_err().to_string().starts_with(
+            "invalid entry for platform `linux-riscv64`: unknown variant `from-the-future`"
+        ));
+    }
+
+    #[test]
+    fn zstd_options_require_zstd_format() {
+        let dotslash = r#"#!/usr/bin/env dotslash
+        {
+            "name": "my_tool",
+            "platforms": {
+                "linux-x86_64": {
+                    "size": 123,
+                    "hash": "sha256",
+                    "digest": "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069",
+                    "format": "tar.zst",
+                    "path": "my_tool",
+                    "providers": [],
+                    "zstd": {"window_log_max": 31},
+                },
+                "macos-x86_64": {
+                    "size": 123,
+                    "hash": "sha256",
+                    "digest": "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069",
+                    "format": "tar.gz",
+                    "path": "my_tool",
+                    "providers": [],
+                    "zstd": {"window_log_max": 31},
+                },
+            },
+        }
+        "#;
+        let config_file = parse_file_string(dotslash).unwrap();
+        assert_eq!(
+            config_file
+                .artifact_entry("linux-x86_64")
+                .unwrap()
+                .unwrap()
+                .zstd,
+            Some(ZstdOptions {
+                window_log_max: Some(31),
+                dictionary: None,
+            }),
+        );
+        assert_eq!(
+            format!(
+                "{:#}",
+                config_file.artifact_entry("macos-x86_64").unwrap_err()
+            ),
+            "invalid entry for platform `macos-x86_64`: \
+             `zstd` may only be specified for the `zst` and `tar.zst` formats",
+        );
+    }
+
+    #[test]
+    fn executables_and_symlinks() {
+        let dotslash = r#"#!/usr/bin/env dotslash
+        {
+            "name": "clang",
+            "platforms": {
+                "linux-x86_64": {
+                    "size": 123,
+                    "hash": "sha256",
+                    "digest": "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069",
+                    "format": "zip",
+                    "path": "bin/clang++",
+                    "providers": [],
+                    "executables": ["bin/*"],
+                    "symlinks": {"bin/clang++": "clang"},
+                },
+                "macos-x86_64": {
+                    "size": 123,
+                    "hash": "sha256",
+                    "digest": "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069",
+                    "format": "zip",
+                    "path": "bin/clang++",
+                    "providers": [],
+                    "executables": ["bin/[*"],
+                },
+                "windows-x86_64": {
+                    "size": 123,
+                    "hash": "sha256",
+                    "digest": "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069",
+                    "format": "zip",
+                    "path": "bin/clang++.exe",
+                    "providers": [],
+                    "symlinks": {"../clang++.exe": "clang.exe"},
+                },
+            },
+        }
+        "#;
+        let config_file = parse_file_string(dotslash).unwrap();
+        let entry = config_file.artifact_entry("linux-x86_64").unwrap().unwrap();
+        assert_eq!(entry.executables, vec!["bin/*".to_owned()]);
+        assert_eq!(
+            entry.symlinks,
+            BTreeMap::from([(
+                ArtifactPath::from_str("bin/clang++").unwrap(),
+                "clang".to_owned()
+            )]),
+        );
+        assert!(format!(
+            "{:#}",
+            config_file.artifact_entry("macos-x86_64").unwrap_err()
+        )
+        .starts_with(
+            "invalid entry for platform `macos-x86_64`: invalid `executables` glob `bin/[*`: "
+        ));
+        assert!(config_file.artifact_entry("windows-x86_64").is_err());
+    }
+
+    #[test]
+    fn extract_and_strip_components() {
+        let dotslash = r#"#!/usr/bin/env dotslash
+        {
+            "name": "my_tool",
+            "platforms": {
+                "linux-x86_64": {
+                    "size": 123,
+                    "hash": "sha256",
+                    "digest": "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069",
+                    "format": "tar.gz",
+                    "path": "bin/my_tool",
+                    "providers": [],
+                    "extract": ["bin/*"],
+                    "strip_components": 1,
+                },
+                "macos-x86_64": {
+                    "size": 123,
+                    "hash": "sha256",
+                    "digest": "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069",
+                    "format": "gz",
+                    "path": "my_tool",
+                    "providers": [],
+                    "strip_components": 1,
+                },
+                "windows-x86_64": {
+                    "size": 123,
+                    "hash": "sha256",
+                    "digest": "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069",
+                    "format": "zip",
+                    "path": "my_tool.exe",
+                    "providers": [],
+                    "extract": ["bin/[a-"],
+                },
+            },
+        }
+        "#;
+        let config_file = parse_file_string(dotslash).unwrap();
+        let entry = config_file.artifact_entry("linux-x86_64").unwrap().unwrap();
+        assert_eq!(entry.extract, vec!["bin/*".to_owned()]);
+        assert_eq!(entry.strip_components, 1);
+        assert_eq!(
+            format!(
+                "{:#}",
+                config_file.artifact_entry("macos-x86_64").unwrap_err()
+            ),
+            "invalid entry for platform `macos-x86_64`: \
+             `extract` and `strip_components` may only be specified for archive formats",
+        );
+        assert!(format!(
+            "{:#}",
+            config_file.artifact_entry("windows-x86_64").unwrap_err()
+        )
+        .starts_with(
+            "invalid entry for platform `windows-x86_64`: invalid `extract` glob `bin/[a-`: "
+        ));
+    }
+
+    #[test]
+    fn interpreter() {
+        let dotslash = r#"#!/usr/bin/env dotslash
+        {
+            "name": "my_tool",
+            "platforms": {
+                "linux-x86_64": {
+                    "size": 123,
+                    "hash": "sha256",
+                    "digest": "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069",
+                    "path": "my_tool.py",
+                    "providers": [],
+                    "interpreter": "python3",
+                },
+                "macos-x86_64": {
+                    "size": 123,
+                    "hash": "sha256",
+                    "digest": "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069",
+                    "path": "my_tool.jar",
+                    "providers": [],
+                    "interpreter": ["java", "-jar"],
+                },
+                "windows-x86_64": {
+                    "size": 123,
+                    "hash": "sha256",
+                    "digest": "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069",
+                    "path": "my_tool.jar",
+                    "providers": [],
+                    "interpreter": [],
+                },
+            },
+        }
+        "#;
+        let config_file = parse_file_string(dotslash).unwrap();
+        let entry = config_file.artifact_entry("linux-x86_64").unwrap().unwrap();
+        assert_eq!(
+            entry.interpreter,
+            Some(Interpreter::Program("python3".to_owned())),
+        );
+        assert_eq!(entry.interpreter.unwrap().argv(), ["python3"]);
+        let entry = config_file.artifact_entry("macos-x86_64").unwrap().unwrap();
+        assert_eq!(entry.interpreter.unwrap().argv(), ["java", "-jar"]);
+        assert_eq!(
+            format!(
+                "{:#}",
+                config_file.artifact_entry("windows-x86_64").unwrap_err()
+            ),
+            "invalid entry for platform `windows-x86_64`: `interpreter` must name a program",
         );
     }
 
@@ -0,0 +1,333 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Credentials for HTTP requests, from a credential helper or a netrc file.
+//!
+//! A credential helper speaks the same protocol as git's `credential.helper`:
+//! it is run with the argument `get`, is sent `protocol=...` and `host=...`
+//! lines on stdin, and prints `username=...` and `password=...` lines (or
+//! `authtype=...` and `credential=...` lines) to stdout. A helper that prints
+//! nothing has no credentials for the host.
+//!
+//! https://git-scm.com/docs/git-credential#IOFMT
+
+use std::collections::HashMap;
+use std::env;
+use std::io;
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+
+use thiserror::Error;
+
+use crate::util::display::CommandDisplay;
+use crate::util::display::CommandStderrDisplay;
+
+/// Command line of the credential helper. It is split on whitespace, so the
+/// program and its arguments cannot contain spaces.
+pub const CREDENTIAL_HELPER_ENV_VAR: &str = "DOTSLASH_CREDENTIAL_HELPER";
+
+/// The helper's answer for each `(protocol, host)`.
+type HelperCache = Mutex<HashMap<(String, String), Option<String>>>;
+
+#[derive(Debug, Error)]
+pub enum CredentialsError {
+    #[error("failed to read `{0}`")]
+    ReadNetrc(PathBuf, #[source] io::Error),
+
+    #[error("failed to execute credential helper `{0}`")]
+    ExecuteHelper(String, #[source] io::Error),
+
+    #[error("credential helper `{0}` failed: {1}")]
+    HelperFailed(String, String),
+}
+
+/// Returns the value for an `Authorization` header for `url`, if the
+/// credential helper or the netrc file has credentials for its host.
+pub fn authorization_for(url: &str) -> Result<Option<String>, CredentialsError> {
+    let Some((protocol, host)) = protocol_and_host(url) else {
+        return Ok(None);
+    };
+
+    if let Ok(helper) = env::var(CREDENTIAL_HELPER_ENV_VAR) {
+        if !helper.trim().is_empty() {
+            // Helpers may prompt or talk to a remote service, so only ask
+            // once per host.
+            static CACHE: OnceLock<HelperCache> = OnceLock::new();
+            let cache = CACHE.get_or_init(Default::default);
+            let key = (protocol.to_owned(), host.to_owned());
+            let cached = cache.lock().unwrap().get(&key).cloned();
+            let authorization = match cached {
+                Some(authorization) => authorization,
+                None => {
+                    let authorization = run_helper(&helper, protocol, host)?;
+                    cache.lock().unwrap().insert(key, authorization.clone());
+                    authorization
+                }
+            };
+            if authorization.is_some() {
+                return Ok(authorization);
+            }
+        }
+    }
+
+    let Some(netrc_path) = netrc_path() else {
+        return Ok(None);
+    };
+    let netrc = match std::fs::read_to_string(&netrc_path) {
+        Ok(netrc) => netrc,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(CredentialsError::ReadNetrc(netrc_path, e)),
+    };
+    // netrc entries are keyed by the host without the port.
+    let hostname = host.rsplit_once(':').map_or(host, |(hostname, _)| hostname);
+    Ok(netrc_lookup(&netrc, hostname).map(|(login, password)| basic(&login, &password)))
+}
+
+fn run_helper(
+    helper: &str,
+    protocol: &str,
+    host: &str,
+) -> Result<Option<String>, CredentialsError> {
+    let mut args = helper.split_ascii_whitespace();
+    let mut command = Command::new(args.next().unwrap_or_default());
+    command
+        .args(args)
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    let execute_error = |e| CredentialsError::ExecuteHelper(helper.to_owned(), e);
+
+    let mut child = command.spawn().map_err(execute_error)?;
+    let mut stdin = child.stdin.take().unwrap();
+    // A helper that does not read its input should not make this fail.
+    let _ = write!(stdin, "protocol={}\nhost={}\n\n", protocol, host);
+    drop(stdin);
+    let output = child.wait_with_output().map_err(execute_error)?;
+    if !output.status.success() {
+        return Err(CredentialsError::HelperFailed(
+            CommandDisplay::new(&command).to_string(),
+            CommandStderrDisplay::new(&output).to_string(),
+        ));
+    }
+
+    Ok(parse_helper_output(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+fn parse_helper_output(output: &str) -> Option<String> {
+    let attributes = output
+        .lines()
+        .filter_map(|line| line.split_once('='))
+        .collect::<HashMap<_, _>>();
+    match (
+        attributes.get("authtype"),
+        attributes.get("credential"),
+        attributes.get("username"),
+        attributes.get("password"),
+    ) {
+        (Some(authtype), Some(credential), _, _) => Some(format!("{} {}", authtype, credential)),
+        (_, _, Some(username), Some(password)) => Some(basic(username, password)),
+        _ => None,
+    }
+}
+
+fn netrc_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("NETRC") {
+        return Some(PathBuf::from(path));
+    }
+    let home = dirs::home_dir()?;
+    let path = home.join(".netrc");
+    if cfg!(windows) && !path.exists() {
+        return Some(home.join("_netrc"));
+    }
+    Some(path)
+}
+
+/// Returns the login and password of the first `machine` entry for `host`,
+/// or of the `default` entry.
+///
+/// https://www.gnu.org/software/inetutils/manual/html_node/The-_002enetrc-file.html
+fn netrc_lookup(netrc: &str, host: &str) -> Option<(String, String)> {
+    #[derive(Default)]
+    struct Entry<'a> {
+        is_match: bool,
+        login: Option<&'a str>,
+        password: Option<&'a str>,
+    }
+
+    let mut words = Vec::new();
+    let mut lines = netrc.lines();
+    while let Some(line) = lines.next() {
+        for word in line.split_ascii_whitespace() {
+            if word == "macdef" {
+                // The macro definition continues until an empty line.
+                for line in lines.by_ref() {
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                }
+                break;
+            }
+            words.push(word);
+        }
+    }
+
+    let mut entries = Vec::<Entry>::new();
+    let mut words = words.into_iter();
+    while let Some(word) = words.next() {
+        match word {
+            "machine" => entries.push(Entry {
+                is_match: words.next().is_some_and(|m| m.eq_ignore_ascii_case(host)),
+                ..Default::default()
+            }),
+            "default" => entries.push(Entry {
+                is_match: true,
+                ..Default::default()
+            }),
+            "login" | "password" | "account" => {
+                let value = words.next();
+                if let Some(entry) = entries.last_mut() {
+                    match word {
+                        "login" => entry.login = value,
+                        "password" => entry.password = value,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    entries
+        .into_iter()
+        .find(|entry| entry.is_match)
+        .and_then(|entry| Some((entry.login?.to_owned(), entry.password?.to_owned())))
+}
+
+/// Splits `https://user@host:port/path` into `https` and `host:port`.
+fn protocol_and_host(url: &str) -> Option<(&str, &str)> {
+    let (protocol, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    if host.is_empty() {
+        None
+    } else {
+        Some((protocol, host))
+    }
+}
+
+fn basic(username: &str, password: &str) -> String {
+    format!(
+        "Basic {}",
+        base64(format!("{}:{}", username, password).as_bytes())
+    )
+}
+
+fn base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut output = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                output.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+        assert_eq!(base64(b"foob"), "Zm9vYg==");
+        assert_eq!(
+            base64(b"Aladdin:open sesame"),
+            "QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+    }
+
+    #[test]
+    fn test_protocol_and_host() {
+        assert_eq!(
+            protocol_and_host("https://artifacts.example.com/tools/a.tar.gz"),
+            Some(("https", "artifacts.example.com")),
+        );
+        assert_eq!(
+            protocol_and_host("http://user@localhost:8080?x=1"),
+            Some(("http", "localhost:8080")),
+        );
+        assert_eq!(protocol_and_host("not a url"), None);
+        assert_eq!(protocol_and_host("https:///path"), None);
+    }
+
+    #[test]
+    fn test_netrc_lookup() {
+        let netrc = "\
+            machine artifacts.example.com login alice password s3cr3t\n\
+            macdef init\n\
+            machine evil.example.com login mallory password x\n\
+            \n\
+            machine nexus.example.com\n\
+            \tlogin bob\n\
+            \tpassword hunter2\n\
+            default login anonymous password guest\n";
+        assert_eq!(
+            netrc_lookup(netrc, "artifacts.example.com"),
+            Some(("alice".to_owned(), "s3cr3t".to_owned())),
+        );
+        assert_eq!(
+            netrc_lookup(netrc, "NEXUS.example.com"),
+            Some(("bob".to_owned(), "hunter2".to_owned())),
+        );
+        // Lines in a macro definition are not entries.
+        assert_eq!(
+            netrc_lookup(netrc, "evil.example.com"),
+            Some(("anonymous".to_owned(), "guest".to_owned())),
+        );
+        assert_eq!(netrc_lookup("machine a login b", "a"), None);
+        assert_eq!(netrc_lookup("", "a"), None);
+    }
+
+    #[test]
+    fn test_parse_helper_output() {
+        assert_eq!(
+            parse_helper_output("username=Aladdin\npassword=open sesame\n"),
+            Some("Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==".to_owned()),
+        );
+        assert_eq!(
+            parse_helper_output("authtype=Bearer\ncredential=abc.def\nusername=x\n"),
+            Some("Bearer abc.def".to_owned()),
+        );
+        assert_eq!(parse_helper_output("username=Aladdin\n"), None);
+        assert_eq!(parse_helper_output(""), None);
+    }
+}
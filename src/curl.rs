@@ -7,37 +7,29 @@
  * of this source tree.
  */
 
+//! The default `HttpBackend`, which runs the system `curl`.
+
 use std::error::Error as StdError;
-use std::ffi::OsStr;
 use std::fmt;
 use std::io;
 use std::path::Path;
 use std::process::Command;
 use std::process::Output;
-use std::thread;
-use std::time::Duration;
+use std::process::Stdio;
 
 use thiserror::Error;
 
-use crate::progress::display_progress;
+use crate::http_client::origin;
+use crate::http_client::HttpBackend;
+use crate::http_client::HttpError;
+use crate::http_client::HttpRequest;
+use crate::http_client::MAX_REDIRECTS;
+use crate::http_client::NUM_TRANSIENT_ERROR_MAX_ATTEMPTS;
+use crate::http_client::USER_AGENT;
 use crate::util::display::CommandDisplay;
 use crate::util::display::CommandStderrDisplay;
 use crate::util::http_status::HttpStatus;
 
-const NUM_RETRYABLE_CURL_MAX_ATTEMPTS: u8 = 3;
-const NUM_TRANSIENT_ERROR_CURL_MAX_ATTEMPTS: u64 = 3;
-
-/// Specify a custom user-agent when making requests. In the unfortunate event
-/// that a site hosting an artifact gets overloaded with requests, hopefully
-/// this will help them identify whether DotSlash is involed.
-const USER_AGENT: &str = concat!(
-    "Mozilla/5.0 (compatible; DotSlash/",
-    env!("CARGO_PKG_VERSION"),
-    "; +",
-    env!("CARGO_PKG_HOMEPAGE"),
-    ")"
-);
-
 // curl exit codes: https://man.cx/curl#heading10
 const CURL_RETRYABLE_EXIT_CODES: &[i32] = &[
     18, // Partial file. Only a part of the file was transferred.
@@ -57,15 +49,21 @@ const CURL_RETRYABLE_EXIT_CODES: &[i32] = &[
 // https://curl.haxx.se/docs/manpage.html#-f
 const CURL_HTTP_RETURNED_ERROR_EXIT_CODE: i32 = 22;
 
+/// Used with --output to discard the response body.
+const NULL_DEVICE: &str = if cfg!(windows) { "NUL" } else { "/dev/null" };
+
+/// Used with --write-out when redirects are not followed. It is appended to
+/// stdout after the response body or headers, on a line of its own.
+const REDIRECT_WRITE_OUT: &str = "\n%{http_code} %{redirect_url}";
+
 enum CurlRequestType<'a> {
     /// String is the argument to use with --output.
     Get(&'a str),
-}
-
-pub struct FetchContext<'a> {
-    pub artifact_name: &'a str,
-    pub content_length: u64,
-    pub show_progress: bool,
+    /// The response body is written to stdout.
+    GetBody,
+    /// Only the response headers are written to stdout. Unlike the other
+    /// request types, an HTTP error status is not treated as a failure.
+    GetHeaders,
 }
 
 #[derive(Debug, Error)]
@@ -82,20 +80,16 @@ pub enum CurlError {
     #[error("`{0}`")]
     HttpStatus(DebugCommand, #[source] HttpStatus),
 
-    // DotSlash failed managing the thread responsible for displaying a progress
-    // indicator.
-    #[error("progress indicator thread panicked with `{0}`")]
-    JoinProgressThread(String),
+    // There were more than `MAX_REDIRECTS` redirects that `run()` followed
+    // itself.
+    #[error("too many redirects from `{0}`")]
+    TooManyRedirects(String),
 }
 
 #[derive(Debug)]
 pub struct CurlExit(Output);
 
-#[derive(Debug)]
-pub struct CurlCommand<'a> {
-    url: &'a OsStr,
-    retry: u64,
-}
+pub struct CurlBackend;
 
 #[derive(Debug)]
 pub struct DebugCommand(Command);
@@ -148,7 +142,7 @@ impl CurlError {
         CurlError::CurlExit(command.into(), CurlExit(output))
     }
 
-    fn is_retryable(&self) -> bool {
+    pub fn is_retryable(&self) -> bool {
         if let Self::CurlExit(_, source) = self {
             if let Some(exit_code) = source.0.status.code() {
                 return CURL_RETRYABLE_EXIT_CODES.contains(&exit_code);
@@ -157,138 +151,219 @@ impl CurlError {
         false
     }
 
-    fn is_too_many_requests(&self) -> bool {
+    pub fn is_too_many_requests(&self) -> bool {
         matches!(self, Self::HttpStatus(_, HttpStatus::TooManyRequests))
     }
 }
 
-impl CurlCommand<'_> {
-    pub fn new(url: &OsStr) -> CurlCommand {
-        CurlCommand {
-            url,
-            retry: NUM_TRANSIENT_ERROR_CURL_MAX_ATTEMPTS,
-        }
-    }
-
-    pub fn get_request(&self, target: &Path, context: &FetchContext) -> Result<(), CurlError> {
+impl HttpBackend for CurlBackend {
+    fn get_to_file(&self, request: &HttpRequest, target: &Path) -> Result<(), HttpError> {
         // Because `target` is ultimately used with Command.args(), we should make
         // it possible to use a non-utf8 value, as unlikely as it is, in practice.
         let output_arg = target.to_str().unwrap();
+        run(request, &CurlRequestType::Get(output_arg))?;
+        Ok(())
+    }
 
-        // TODO: Implement a progress handler that prints dots so long as the curl
-        // request is still running. For now, just treat it as if the user had not
-        // requested a progress bar.
+    fn get_body(&self, request: &HttpRequest) -> Result<Vec<u8>, HttpError> {
+        Ok(run(request, &CurlRequestType::GetBody)?)
+    }
 
-        // While making the request, poll the target and report what percentage
-        // done it is compared to content_length.
-        let handler = if context.show_progress {
-            eprintln!("Downloading {}...", context.artifact_name);
-            Some(display_progress(context.content_length, target))
-        } else {
-            None
-        };
+    fn get_headers(
+        &self,
+        request: &HttpRequest,
+    ) -> Result<(u16, Vec<(String, String)>), HttpError> {
+        let stdout = run(request, &CurlRequestType::GetHeaders)?;
+        Ok(parse_response_headers(&String::from_utf8_lossy(&stdout)))
+    }
+}
 
-        // If the request fails, the `progress_sender` channel is dropped,
-        // and the progress thread uses this to finish by itself.
-        self.make_request(&mut self.curl_command(self.url, &CurlRequestType::Get(output_arg)))?;
-
-        if let Some((progress_sender, join_handler)) = handler {
-            // Let the progress thread know that we're done done.
-            // It may already know this if the content size information
-            // is correct.
-            let _ = progress_sender.send(());
-            join_handler
-                .join()
-                .map_err(|e| CurlError::JoinProgressThread(format!("{:?}", e)))?;
-        }
+/// curl sends the extra headers on every redirect, and it only drops
+/// `Authorization` and `Cookie` when the host changes. So if there are extra
+/// headers, redirects are followed here instead until one leaves the origin of
+/// the request, after which curl follows the rest without the headers.
+fn run(request: &HttpRequest, request_type: &CurlRequestType) -> Result<Vec<u8>, CurlError> {
+    if request.headers.is_empty() {
+        return run_once(request, request_type, /* follow_redirects */ true);
+    }
 
-        Ok(())
+    let mut url = request.url.to_owned();
+    for _ in 0..MAX_REDIRECTS {
+        let hop = HttpRequest {
+            url: &url,
+            headers: request.headers.clone(),
+        };
+        let mut stdout = run_once(&hop, request_type, /* follow_redirects */ false)?;
+        let Some(location) = take_redirect_location(&mut stdout) else {
+            return Ok(stdout);
+        };
+        if origin(&location) != origin(request.url) {
+            return run_once(
+                &HttpRequest::new(&location),
+                request_type,
+                /* follow_redirects */ true,
+            );
+        }
+        url = location;
     }
+    Err(CurlError::TooManyRedirects(request.url.to_owned()))
+}
 
-    fn make_request(&self, curl_command: &mut Command) -> Result<Vec<u8>, CurlError> {
-        let mut retries = 1..=NUM_RETRYABLE_CURL_MAX_ATTEMPTS;
-        loop {
-            let output = match curl_command.output() {
-                Ok(output) => output,
-                // If curl failed to execute, exit immediately.
-                Err(e) => return Err(CurlError::from_command_error(curl_command, e)),
-            };
+/// Removes the output of `REDIRECT_WRITE_OUT` from `stdout`, and returns the
+/// absolute URL to redirect to, if any.
+fn take_redirect_location(stdout: &mut Vec<u8>) -> Option<String> {
+    let newline = stdout.iter().rposition(|&b| b == b'\n')?;
+    let write_out = String::from_utf8_lossy(&stdout[newline + 1..]).into_owned();
+    stdout.truncate(newline);
+    let (status, location) = write_out.split_once(' ')?;
+    match status {
+        "301" | "302" | "303" | "307" | "308" if !location.is_empty() => Some(location.to_owned()),
+        _ => None,
+    }
+}
 
-            if output.status.success() {
-                // curl completed successfully!
-                return Ok(output.stdout);
-            }
+fn run_once(
+    request: &HttpRequest,
+    request_type: &CurlRequestType,
+    follow_redirects: bool,
+) -> Result<Vec<u8>, CurlError> {
+    let mut curl_command = curl_command(request, request_type, follow_redirects);
+    let output = match spawn(&mut curl_command, &request.headers) {
+        Ok(output) => output,
+        Err(e) => return Err(CurlError::from_command_error(&curl_command, e)),
+    };
+
+    if output.status.success() {
+        // curl completed successfully!
+        Ok(output.stdout)
+    } else {
+        Err(CurlError::from_command_output(&curl_command, output))
+    }
+}
 
-            let curl_error = CurlError::from_command_output(curl_command, output);
-
-            // Sometimes we manually retry ourselves...
-            if let Some(retry_num) = retries.next() {
-                // curl failed, but it satisfies our "retryable" heuristic,
-                // so loop again.
-                if curl_error.is_retryable() {
-                    continue;
-                }
-
-                // curl failed, but we're hitting the server too hard,
-                // so loop again, but wait a little bit.
-                if curl_error.is_too_many_requests() {
-                    // 1^3=1s  ->  2^3=8s  ->  3^3=27s
-                    thread::sleep(Duration::from_secs(retry_num.pow(3) as u64));
-                    continue;
-                }
-            }
+/// The extra headers are written to curl's stdin rather than passed as
+/// arguments so that credentials do not show up in the process list or in a
+/// `DebugCommand`.
+fn spawn(curl_command: &mut Command, headers: &[(String, String)]) -> io::Result<Output> {
+    if headers.is_empty() {
+        return curl_command.output();
+    }
 
-            return Err(curl_error);
-        }
+    let mut child = curl_command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().unwrap();
+    if let Err(err) = write_headers(&mut stdin, headers) {
+        drop(stdin);
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(err);
     }
+    drop(stdin);
+    child.wait_with_output()
+}
 
-    fn curl_command(&self, url: &OsStr, request_type: &CurlRequestType) -> Command {
-        let mut curl_command = Command::new("curl");
+fn curl_command(
+    request: &HttpRequest,
+    request_type: &CurlRequestType,
+    follow_redirects: bool,
+) -> Command {
+    let mut curl_command = Command::new("curl");
 
-        // https://cygwin.com/cygwin-ug-net/using-cygwinenv.html
-        if cfg!(windows) {
-            curl_command.env("CYGWIN", "noglob").env("MSYS", "noglob");
-        }
+    // https://cygwin.com/cygwin-ug-net/using-cygwinenv.html
+    if cfg!(windows) {
+        curl_command.env("CYGWIN", "noglob").env("MSYS", "noglob");
+    }
 
-        // Follow redirects.
+    // Follow redirects, or report where to redirect to. See `run()`.
+    if follow_redirects {
         curl_command.arg("--location");
+    } else {
+        curl_command.args(["--write-out", REDIRECT_WRITE_OUT]);
+    }
 
-        //
-        // https://curl.haxx.se/docs/manpage.html
-        //
+    //
+    // https://curl.haxx.se/docs/manpage.html
+    //
 
-        // If a transient error is returned when curl tries to perform a
-        // transfer, it will retry this number of times before giving up.
-        curl_command.arg("--retry");
-        curl_command.arg(self.retry.to_string());
+    // If a transient error is returned when curl tries to perform a
+    // transfer, it will retry this number of times before giving up.
+    curl_command.arg("--retry");
+    curl_command.arg(NUM_TRANSIENT_ERROR_MAX_ATTEMPTS.to_string());
 
-        // When an HTTP server fails to deliver a document, it returns an
-        // HTML document stating so. This flag will prevent curl from
-        // outputting that and return error 22.
-        // (In other words, fail on 404 - expired or bad handle)
+    // When an HTTP server fails to deliver a document, it returns an
+    // HTML document stating so. This flag will prevent curl from
+    // outputting that and return error 22.
+    // (In other words, fail on 404 - expired or bad handle)
+    if !matches!(request_type, CurlRequestType::GetHeaders) {
         curl_command.arg("--fail");
+    }
+
+    // Silent or quiet mode. Don't show progress meter or error messages.
+    // Makes Curl mute.
+    curl_command.arg("--silent");
 
-        // Silent or quiet mode. Don't show progress meter or error messages.
-        // Makes Curl mute.
-        curl_command.arg("--silent");
+    // When used with -s, --silent, it makes curl show an error message if
+    // it fails.
+    curl_command.arg("--show-error");
 
-        // When used with -s, --silent, it makes curl show an error message if
-        // it fails.
-        curl_command.arg("--show-error");
+    curl_command.arg("--user-agent");
+    curl_command.arg(USER_AGENT);
 
-        curl_command.arg("--user-agent");
-        curl_command.arg(USER_AGENT);
+    // Read the extra headers from stdin. See `spawn()`.
+    if !request.headers.is_empty() {
+        curl_command.args(["--header", "@-"]);
+    }
 
-        curl_command.arg(url);
+    curl_command.arg(request.url);
 
-        match request_type {
-            CurlRequestType::Get(output) => {
-                curl_command.args(["--output", output]);
-            }
+    match request_type {
+        CurlRequestType::Get(output) => {
+            curl_command.args(["--output", output]);
         }
+        CurlRequestType::GetBody => {}
+        CurlRequestType::GetHeaders => {
+            curl_command.args(["--dump-header", "-", "--output", NULL_DEVICE]);
+        }
+    }
 
-        curl_command
+    curl_command
+}
+
+fn write_headers<W: io::Write>(writer: &mut W, headers: &[(String, String)]) -> io::Result<()> {
+    for (name, value) in headers {
+        // A line break would let a value smuggle in another header.
+        if name.contains(['\r', '\n']) || value.contains(['\r', '\n']) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("header `{name}` contains a line break"),
+            ));
+        }
+        writeln!(writer, "{name}: {value}")?;
+    }
+    Ok(())
+}
+
+/// Parses the output of `--dump-header`, which contains one block of headers
+/// per response when redirects are followed.
+fn parse_response_headers(dump: &str) -> (u16, Vec<(String, String)>) {
+    let mut status = 0;
+    let mut headers = Vec::new();
+    for line in dump.lines() {
+        if line.starts_with("HTTP/") {
+            status = line
+                .split_ascii_whitespace()
+                .nth(1)
+                .and_then(|code| code.parse().ok())
+                .unwrap_or(0);
+            headers.clear();
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_owned(), value.trim().to_owned()));
+        }
     }
+    (status, headers)
 }
 
 fn parse_http_returned_error(stderr: &[u8]) -> Option<usize> {
@@ -342,17 +417,74 @@ mod tests {
     }
 
     #[test]
-    fn user_agent() {
-        let version = env!("CARGO_PKG_VERSION");
-        // For reference, the user-agent that Google sends from its webcrawler is:
-        //
-        //     Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)
-        //
-        // The purpose of this test is to make it easier to visually verify that
-        // the user-agent is what we expect.
+    fn test_parse_response_headers() {
         assert_eq!(
-            format!("Mozilla/5.0 (compatible; DotSlash/{version}; +https://dotslash-cli.com)"),
-            USER_AGENT
+            parse_response_headers(
+                "HTTP/1.1 401 Unauthorized\r\n\
+                 Content-Type: application/json\r\n\
+                 Www-Authenticate: Bearer realm=\"https://ghcr.io/token\",service=\"ghcr.io\"\r\n\
+                 \r\n",
+            ),
+            (
+                401,
+                vec![
+                    ("Content-Type".to_owned(), "application/json".to_owned()),
+                    (
+                        "Www-Authenticate".to_owned(),
+                        "Bearer realm=\"https://ghcr.io/token\",service=\"ghcr.io\"".to_owned(),
+                    ),
+                ],
+            ),
+        );
+        assert_eq!(
+            parse_response_headers(
+                "HTTP/2 307\r\nlocation: /v2/\r\n\r\nHTTP/2 200\r\ncontent-length: 2\r\n\r\n",
+            ),
+            (200, vec![("content-length".to_owned(), "2".to_owned())]),
+        );
+        assert_eq!(parse_response_headers(""), (0, vec![]));
+    }
+
+    #[test]
+    fn test_take_redirect_location() {
+        let mut stdout = b"<html>moved\n</html>\n302 https://storage.example.com/a".to_vec();
+        assert_eq!(
+            take_redirect_location(&mut stdout).as_deref(),
+            Some("https://storage.example.com/a"),
         );
+        assert_eq!(stdout, b"<html>moved\n</html>");
+
+        let mut stdout = b"body\n200 ".to_vec();
+        assert_eq!(take_redirect_location(&mut stdout), None);
+        assert_eq!(stdout, b"body");
+
+        let mut stdout = b"\n304 ".to_vec();
+        assert_eq!(take_redirect_location(&mut stdout), None);
+        assert_eq!(stdout, b"");
+    }
+
+    #[test]
+    fn test_write_headers() {
+        let mut buf = Vec::new();
+        write_headers(
+            &mut buf,
+            &[
+                ("Accept".to_owned(), "application/json".to_owned()),
+                ("Authorization".to_owned(), "Bearer abc".to_owned()),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "Accept: application/json\nAuthorization: Bearer abc\n",
+        );
+
+        let err = write_headers(
+            &mut Vec::new(),
+            &[("X-Foo".to_owned(), "bar\r\nX-Bar: baz".to_owned())],
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(err.to_string(), "header `X-Foo` contains a line break");
     }
 }
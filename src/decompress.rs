@@ -7,17 +7,150 @@
  * of this source tree.
  */
 
+use std::env;
+use std::fmt;
 use std::io;
+use std::io::BufReader;
 use std::io::Read;
+use std::path::Component;
 use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use tar::EntryType;
 
 use crate::util::fs_ctx;
 
+/// Overrides the limit on the total size of the files in an archive.
+pub const MAX_EXTRACTED_BYTES_ENV_VAR: &str = "DOTSLASH_MAX_EXTRACTED_BYTES";
+
+/// Overrides the limit on the number of entries in an archive.
+pub const MAX_ARCHIVE_ENTRIES_ENV_VAR: &str = "DOTSLASH_MAX_ARCHIVE_ENTRIES";
+
+const DEFAULT_MAX_EXTRACTED_BYTES: u64 = 32 << 30;
+const DEFAULT_MAX_ARCHIVE_ENTRIES: u64 = 1_000_000;
+
+/// Options for matching the globs in a DotSlash file against relative paths:
+/// `*` does not match `/`, so `**` is needed to match across directories.
+pub const GLOB_MATCH_OPTIONS: glob::MatchOptions = glob::MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+const S_IFMT: u32 = 0o170000;
+const S_IFLNK: u32 = 0o120000;
+
+/// Limits that protect against archives that expand to far more than their
+/// compressed size would suggest.
+pub struct ExtractionLimits {
+    max_bytes: u64,
+    max_entries: u64,
+    bytes: u64,
+    entries: u64,
+}
+
+impl ExtractionLimits {
+    pub fn new(max_bytes: u64, max_entries: u64) -> ExtractionLimits {
+        ExtractionLimits {
+            max_bytes,
+            max_entries,
+            bytes: 0,
+            entries: 0,
+        }
+    }
+
+    pub fn from_env() -> io::Result<ExtractionLimits> {
+        let limit = |var, default| match env::var(var) {
+            Ok(value) => value.trim().parse::<u64>().map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("invalid value for `{}`: `{}`", var, value),
+                )
+            }),
+            Err(_) => Ok(default),
+        };
+        Ok(ExtractionLimits::new(
+            limit(MAX_EXTRACTED_BYTES_ENV_VAR, DEFAULT_MAX_EXTRACTED_BYTES)?,
+            limit(MAX_ARCHIVE_ENTRIES_ENV_VAR, DEFAULT_MAX_ARCHIVE_ENTRIES)?,
+        ))
+    }
+
+    /// Accounts for an entry named `name` with `size` bytes of content.
+    pub fn add_entry(&mut self, name: impl fmt::Display, size: u64) -> io::Result<()> {
+        self.entries += 1;
+        if self.entries > self.max_entries {
+            return Err(invalid_data(format!(
+                "archive entry `{}` exceeds the limit of {} entries (see ${})",
+                name, self.max_entries, MAX_ARCHIVE_ENTRIES_ENV_VAR,
+            )));
+        }
+        self.bytes = self.bytes.saturating_add(size);
+        if self.bytes > self.max_bytes {
+            return Err(invalid_data(format!(
+                "archive entry `{}` exceeds the limit of {} extracted bytes (see ${})",
+                name, self.max_bytes, MAX_EXTRACTED_BYTES_ENV_VAR,
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Selects the entries of an archive to unpack and where to unpack them,
+/// per the `extract` and `strip_components` fields of an artifact entry.
+#[derive(Default)]
+pub struct EntryFilter {
+    include: Vec<glob::Pattern>,
+    strip_components: usize,
+}
+
+impl EntryFilter {
+    pub fn new(include: &[String], strip_components: usize) -> anyhow::Result<EntryFilter> {
+        let include = include
+            .iter()
+            .map(|pattern| {
+                glob::Pattern::new(pattern)
+                    .with_context(|| format!("invalid `extract` glob `{}`", pattern))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(EntryFilter {
+            include,
+            strip_components,
+        })
+    }
+
+    /// Maps the relative path of an entry to the path to unpack it to, or
+    /// returns `None` if the entry should be skipped. Entries are matched
+    /// against the `include` globs after their leading components have
+    /// been stripped, and entries with no components left are skipped.
+    pub fn map(&self, relative_path: &Path) -> Option<PathBuf> {
+        let path = relative_path
+            .components()
+            .skip(self.strip_components)
+            .collect::<PathBuf>();
+        let is_included = self.include.is_empty()
+            || self
+                .include
+                .iter()
+                .any(|pattern| pattern.matches_path_with(&path, GLOB_MATCH_OPTIONS));
+        if path.as_os_str().is_empty() || !is_included {
+            None
+        } else {
+            Some(path)
+        }
+    }
+}
+
 /// Attempts to extract the tar archive into the specified directory.
 /// To extract, it uses the tar crate (https://crates.io/crates/tar) directly.
 /// Those who create compressed artifacts for DotSlash are responsible for
 /// ensuring they can be decompressed with its version of tar.
-pub fn untar(tar_file: &Path, destination_dir: &Path, is_tar_gz: bool) -> io::Result<()> {
+pub fn untar(
+    tar_file: &Path,
+    destination_dir: &Path,
+    is_tar_gz: bool,
+    filter: &EntryFilter,
+) -> io::Result<()> {
     // The destination dir is canonicalized for the benefit of Windows, but we
     // do it on all platforms for consistency of behavior.
     //
@@ -46,15 +179,599 @@ pub fn untar(tar_file: &Path, destination_dir: &Path, is_tar_gz: bool) -> io::Re
     if is_tar_gz {
         let decoder = flate2::read::GzDecoder::new(file);
         let archive = tar::Archive::new(decoder);
-        unpack(archive, &destination_dir)
+        unpack(archive, &destination_dir, filter)
     } else {
         let archive = tar::Archive::new(file);
-        unpack(archive, &destination_dir)
+        unpack(archive, &destination_dir, filter)
     }
 }
 
-pub fn unpack<R: Read>(mut archive: tar::Archive<R>, destination_dir: &Path) -> io::Result<()> {
+/// Attempts to extract the entries of the zip archive selected by `filter`
+/// into the specified directory, keeping the Unix permission bits recorded for
+/// each entry. Entries (including the targets of symlinks) that would end up
+/// outside of `destination_dir` are rejected.
+pub fn unzip(zip_file: &Path, destination_dir: &Path, filter: &EntryFilter) -> io::Result<()> {
+    // See untar() for why the destination dir is canonicalized.
+    fs_ctx::create_dir_all(destination_dir)?;
+    let destination_dir = fs_ctx::canonicalize(destination_dir)?;
+    let file = fs_ctx::file_open(zip_file)?;
+    let mut archive = zip::ZipArchive::new(BufReader::new(file))?;
+    let mut limits = ExtractionLimits::from_env()?;
+
+    // Like tar::Archive::unpack(), permissions for directories are applied
+    // last so that a read-only directory can still be populated. Symlinks
+    // are also created last so that no entry is written through one.
+    let mut dir_modes = Vec::new();
+    let mut symlinks = Vec::new();
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let relative_path = entry.enclosed_name().map(Path::to_owned).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("zip entry `{}` escapes the destination", entry.name()),
+            )
+        })?;
+        limits.add_entry(entry.name(), entry.size())?;
+        let Some(relative_path) = filter.map(&relative_path) else {
+            continue;
+        };
+        let path = destination_dir.join(relative_path);
+        let mode = entry.unix_mode();
+
+        if entry.is_dir() {
+            fs_ctx::create_dir_all(&path)?;
+            if let Some(mode) = mode {
+                dir_modes.push((path, mode));
+            }
+            continue;
+        }
+
+        if let Some(parent) = path.parent() {
+            fs_ctx::create_dir_all(parent)?;
+        }
+        if mode.is_some_and(|mode| mode & S_IFMT == S_IFLNK) {
+            let mut target = String::new();
+            entry.read_to_string(&mut target)?;
+            symlinks.push((entry.name().to_owned(), path, target));
+            continue;
+        }
+
+        let mut output = fs_ctx::file_create(&path)?;
+        // Only the declared size was checked against the limits.
+        let size = entry.size();
+        io::copy(&mut (&mut entry).take(size), &mut output)?;
+        drop(output);
+        #[cfg(unix)]
+        if let Some(mode) = mode {
+            set_unix_permissions(&path, mode)?;
+        }
+    }
+
+    for (name, path, target) in symlinks {
+        create_symlink(&destination_dir, &name, &path, &target)?;
+    }
+
+    #[cfg(unix)]
+    for (path, mode) in dir_modes.into_iter().rev() {
+        set_unix_permissions(&path, mode)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(unix)]
+pub fn set_unix_permissions(path: &Path, mode: u32) -> io::Result<()> {
+    use std::os::unix::fs::PermissionsExt as _;
+    // Only the permission bits are kept: setuid, setgid, and sticky bits
+    // have no business in the DotSlash cache.
+    fs_ctx::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o777))
+}
+
+/// Creates a symlink for the archive entry `name` at `path`, which must be
+/// inside `destination_dir`, after checking its target with
+/// `is_enclosed_link_target()`.
+pub fn create_symlink(
+    destination_dir: &Path,
+    name: &str,
+    path: &Path,
+    target: &str,
+) -> io::Result<()> {
+    let link_dir = path.parent().unwrap_or(destination_dir);
+    check_link_target(destination_dir, link_dir, name, Path::new(target))?;
+    symlink(target, path)
+}
+
+pub fn symlink(target: &str, path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(target, path)?;
+    #[cfg(windows)]
+    std::os::windows::fs::symlink_file(target, path)?;
+    Ok(())
+}
+
+fn check_link_target(
+    destination_dir: &Path,
+    link_dir: &Path,
+    name: &str,
+    target: &Path,
+) -> io::Result<()> {
+    if is_enclosed_link_target(destination_dir, link_dir, target)? {
+        Ok(())
+    } else {
+        Err(invalid_data(format!(
+            "archive entry `{}` links to `{}`, which is outside of the artifact directory",
+            name,
+            target.display(),
+        )))
+    }
+}
+
+/// Whether a link in `link_dir` whose target is `target` resolves to a path
+/// inside `destination_dir`, which must be canonical. `link_dir` is created
+/// if it does not exist.
+///
+/// Links are checked in the order they are created, so any link the target
+/// descends through has already been checked. To keep that reasoning sound,
+/// `..` is only allowed at the start of the target: after descending through
+/// a link, `..` would be relative to wherever that link points rather than
+/// to its parent.
+pub fn is_enclosed_link_target(
+    destination_dir: &Path,
+    link_dir: &Path,
+    target: &Path,
+) -> io::Result<bool> {
+    fs_ctx::create_dir_all(link_dir)?;
+    let mut resolved = fs_ctx::canonicalize(link_dir)?;
+    let mut is_enclosed = resolved.starts_with(destination_dir);
+    let mut descended = false;
+    for component in target.components() {
+        match component {
+            Component::CurDir => {}
+            Component::Normal(_) => descended = true,
+            Component::ParentDir if !descended && resolved != destination_dir => {
+                resolved.pop();
+            }
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                is_enclosed = false;
+            }
+        }
+    }
+    Ok(is_enclosed)
+}
+
+/// Unpacks the entries of a tar archive selected by `filter` into
+/// `destination_dir` with the following policy:
+///
+/// - Permission bits and mtimes are kept, but setuid, setgid, and sticky
+///   bits are cleared.
+/// - Symlinks and hard links must resolve to a path in `destination_dir`.
+/// - Device nodes and fifos are rejected.
+/// - The total size of the entries and the number of entries are limited
+///   (see `ExtractionLimits`).
+/// - Entries whose path contains `..` are rejected.
+pub fn unpack<R: Read>(
+    archive: tar::Archive<R>,
+    destination_dir: &Path,
+    filter: &EntryFilter,
+) -> io::Result<()> {
+    unpack_with_limits(
+        archive,
+        destination_dir,
+        filter,
+        ExtractionLimits::from_env()?,
+    )
+}
+
+fn unpack_with_limits<R: Read>(
+    mut archive: tar::Archive<R>,
+    destination_dir: &Path,
+    filter: &EntryFilter,
+    mut limits: ExtractionLimits,
+) -> io::Result<()> {
+    // See untar() for why the destination dir is canonicalized.
+    fs_ctx::create_dir_all(destination_dir)?;
+    let destination_dir = fs_ctx::canonicalize(destination_dir)?;
     archive.set_preserve_permissions(true);
     archive.set_preserve_mtime(true);
-    archive.unpack(destination_dir)
+    archive.set_mask(0o7000);
+
+    // Like tar::Archive::unpack(), directories are unpacked last so that
+    // their permissions do not get in the way of unpacking their contents.
+    let mut directories = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.display().to_string();
+        limits.add_entry(&name, entry.size())?;
+        let entry_type = entry.header().entry_type();
+        if matches!(
+            entry_type,
+            EntryType::Block | EntryType::Char | EntryType::Fifo
+        ) {
+            return Err(invalid_data(format!(
+                "archive entry `{}` is a device or fifo, which is not allowed",
+                name,
+            )));
+        }
+
+        let relative_path = enclosed_path(&entry.path()?).ok_or_else(|| {
+            invalid_data(format!(
+                "archive entry `{}` is outside of the artifact directory",
+                name,
+            ))
+        })?;
+        let Some(relative_path) = filter.map(&relative_path) else {
+            continue;
+        };
+        let path = destination_dir.join(relative_path);
+        if let Some(parent) = path.parent() {
+            fs_ctx::create_dir_all(parent)?;
+        }
+
+        match entry_type {
+            EntryType::Symlink => {
+                let target = entry.link_name()?.unwrap_or_default().into_owned();
+                let link_dir = path.parent().unwrap_or(&destination_dir);
+                check_link_target(&destination_dir, link_dir, &name, &target)?;
+            }
+            EntryType::Link => {
+                // The target of a hard link is the name of an earlier entry,
+                // so it is subject to the same filter. Once mapped, it only
+                // has normal components, so it cannot leave the destination.
+                let target = entry.link_name()?.unwrap_or_default().into_owned();
+                let mapped_target = enclosed_path(&target).ok_or_else(|| {
+                    invalid_data(format!(
+                        "archive entry `{}` links to `{}`, which is outside of the artifact directory",
+                        name,
+                        target.display(),
+                    ))
+                })?;
+                let mapped_target = filter.map(&mapped_target).ok_or_else(|| {
+                    invalid_data(format!(
+                        "archive entry `{}` links to `{}`, which is not extracted",
+                        name,
+                        target.display(),
+                    ))
+                })?;
+                std::fs::hard_link(destination_dir.join(mapped_target), &path)?;
+                continue;
+            }
+            EntryType::Directory => {
+                directories.push((entry, path));
+                continue;
+            }
+            _ => {}
+        }
+        entry.unpack(&path)?;
+    }
+    for (mut directory, path) in directories {
+        directory.unpack(&path)?;
+    }
+    Ok(())
+}
+
+/// Converts the name of an archive entry such as `./usr/bin/tool` into a
+/// relative path, or returns `None` if it has a `..` component. Like the tar
+/// crate, absolute names are treated as relative.
+pub fn enclosed_path(name: &Path) -> Option<PathBuf> {
+    let mut path = PathBuf::new();
+    for component in name.components() {
+        match component {
+            Component::Normal(name) => path.push(name),
+            Component::CurDir | Component::RootDir | Component::Prefix(_) => {}
+            Component::ParentDir => return None,
+        }
+    }
+    Some(path)
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    use super::*;
+
+    fn write_zip(entries: &[(&str, u32, &str)]) -> tempfile::NamedTempFile {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        let mut writer = ZipWriter::new(file.reopen().unwrap());
+        for (name, mode, contents) in entries {
+            let options = FileOptions::default().unix_permissions(*mode);
+            if name.ends_with('/') {
+                writer.add_directory(*name, options).unwrap();
+            } else if mode & S_IFMT == S_IFLNK {
+                writer.add_symlink(*name, *contents, options).unwrap();
+            } else {
+                writer.start_file(*name, options).unwrap();
+                writer.write_all(contents.as_bytes()).unwrap();
+            }
+        }
+        writer.finish().unwrap();
+        file
+    }
+
+    #[test]
+    fn unzip_files_and_directories() {
+        let zip_file = write_zip(&[
+            ("bin/", 0o755, ""),
+            ("bin/tool", 0o755, "#!/bin/sh\n"),
+            ("README", 0o644, "hello\n"),
+        ]);
+        let destination = tempfile::tempdir().unwrap();
+        unzip(zip_file.path(), destination.path(), &EntryFilter::default()).unwrap();
+
+        let tool = destination.path().join("bin/tool");
+        assert_eq!(std::fs::read_to_string(&tool).unwrap(), "#!/bin/sh\n");
+        assert_eq!(
+            std::fs::read_to_string(destination.path().join("README")).unwrap(),
+            "hello\n",
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt as _;
+            let mode = |path: &Path| fs_ctx::metadata(path).unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode(&tool), 0o755);
+            assert_eq!(mode(&destination.path().join("README")), 0o644);
+        }
+    }
+
+    #[test]
+    fn unzip_rejects_path_traversal() {
+        let zip_file = write_zip(&[("../evil", 0o644, "")]);
+        let destination = tempfile::tempdir().unwrap();
+        let err = unzip(zip_file.path(), destination.path(), &EntryFilter::default()).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            "zip entry `../evil` escapes the destination"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unzip_symlinks() {
+        let zip_file = write_zip(&[
+            ("bin/tool", 0o755, "#!/bin/sh\n"),
+            ("tool", S_IFLNK | 0o777, "bin/tool"),
+        ]);
+        let destination = tempfile::tempdir().unwrap();
+        unzip(zip_file.path(), destination.path(), &EntryFilter::default()).unwrap();
+        assert_eq!(
+            std::fs::read_link(destination.path().join("tool")).unwrap(),
+            Path::new("bin/tool"),
+        );
+
+        let zip_file = write_zip(&[("bin/tool", S_IFLNK | 0o777, "../../etc/passwd")]);
+        let err = unzip(
+            zip_file.path(),
+            tempfile::tempdir().unwrap().path(),
+            &EntryFilter::default(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "archive entry `bin/tool` links to `../../etc/passwd`, which is outside of the artifact directory",
+        );
+    }
+
+    fn tar_archive(entries: &[(&str, EntryType, u32, &str)]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (name, entry_type, mode, contents) in entries {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(*entry_type);
+            header.set_mode(*mode);
+            header.set_mtime(0);
+            if matches!(entry_type, EntryType::Symlink | EntryType::Link) {
+                header.set_size(0);
+                header.set_link_name(contents).unwrap();
+                builder.append_data(&mut header, name, io::empty()).unwrap();
+            } else {
+                header.set_size(contents.len() as u64);
+                builder
+                    .append_data(&mut header, name, contents.as_bytes())
+                    .unwrap();
+            }
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn unpack_tar(
+        entries: &[(&str, EntryType, u32, &str)],
+        limits: ExtractionLimits,
+    ) -> io::Result<tempfile::TempDir> {
+        let destination = tempfile::tempdir().unwrap();
+        let archive = tar::Archive::new(io::Cursor::new(tar_archive(entries)));
+        unpack_with_limits(archive, destination.path(), &EntryFilter::default(), limits)?;
+        Ok(destination)
+    }
+
+    fn unlimited() -> ExtractionLimits {
+        ExtractionLimits::new(u64::MAX, u64::MAX)
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unpack_tar_links() {
+        let destination = unpack_tar(
+            &[
+                ("bin/tool", EntryType::Regular, 0o755, "#!/bin/sh\n"),
+                ("tool", EntryType::Symlink, 0o777, "bin/tool"),
+                ("bin/alias", EntryType::Symlink, 0o777, "../tool"),
+                ("bin/copy", EntryType::Link, 0o755, "bin/tool"),
+            ],
+            unlimited(),
+        )
+        .unwrap();
+        for path in ["tool", "bin/alias", "bin/copy"] {
+            assert_eq!(
+                std::fs::read_to_string(destination.path().join(path)).unwrap(),
+                "#!/bin/sh\n",
+            );
+        }
+    }
+
+    #[test]
+    fn unpack_tar_rejects_escaping_links() {
+        for (entries, message) in [
+            (
+                &[("a/b", EntryType::Symlink, 0o777, "../../etc/passwd")][..],
+                "archive entry `a/b` links to `../../etc/passwd`, which is outside of the artifact directory",
+            ),
+            (
+                &[("a", EntryType::Symlink, 0o777, "/etc/passwd")][..],
+                "archive entry `a` links to `/etc/passwd`, which is outside of the artifact directory",
+            ),
+            (
+                // A `..` after a symlink is relative to the symlink's target.
+                &[
+                    ("a/here", EntryType::Symlink, 0o777, "."),
+                    ("a/b", EntryType::Symlink, 0o777, "here/../.."),
+                ][..],
+                "archive entry `a/b` links to `here/../..`, which is outside of the artifact directory",
+            ),
+            (
+                &[("a", EntryType::Link, 0o644, "../etc/passwd")][..],
+                "archive entry `a` links to `../etc/passwd`, which is outside of the artifact directory",
+            ),
+        ] {
+            let err = unpack_tar(entries, unlimited()).unwrap_err();
+            assert_eq!(err.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn unpack_tar_rejects_devices_and_fifos() {
+        for entry_type in [EntryType::Block, EntryType::Char, EntryType::Fifo] {
+            let err = unpack_tar(&[("dev", entry_type, 0o644, "")], unlimited()).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                "archive entry `dev` is a device or fifo, which is not allowed",
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unpack_tar_strips_setuid_and_setgid() {
+        use std::os::unix::fs::PermissionsExt as _;
+
+        let destination = unpack_tar(
+            &[("tool", EntryType::Regular, 0o6755, "#!/bin/sh\n")],
+            unlimited(),
+        )
+        .unwrap();
+        let metadata = std::fs::metadata(destination.path().join("tool")).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o7777, 0o755);
+    }
+
+    #[test]
+    fn unpack_tar_enforces_limits() {
+        let entries = [
+            ("a", EntryType::Regular, 0o644, "12345"),
+            ("b", EntryType::Regular, 0o644, "67890"),
+        ];
+        unpack_tar(&entries, ExtractionLimits::new(10, 2)).unwrap();
+
+        let err = unpack_tar(&entries, ExtractionLimits::new(9, 2)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "archive entry `b` exceeds the limit of 9 extracted bytes (see $DOTSLASH_MAX_EXTRACTED_BYTES)",
+        );
+
+        let err = unpack_tar(&entries, ExtractionLimits::new(10, 1)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "archive entry `b` exceeds the limit of 1 entries (see $DOTSLASH_MAX_ARCHIVE_ENTRIES)",
+        );
+    }
+
+    #[test]
+    fn entry_filter_map() {
+        let filter = EntryFilter::default();
+        assert_eq!(filter.map(Path::new("a/b")), Some(PathBuf::from("a/b")));
+
+        let filter = EntryFilter::new(&["bin/*".to_owned(), "lib/**/*.so".to_owned()], 1).unwrap();
+        assert_eq!(
+            filter.map(Path::new("tool-1.2.3/bin/tool")),
+            Some(PathBuf::from("bin/tool")),
+        );
+        assert_eq!(
+            filter.map(Path::new("tool-1.2.3/lib/a/b/libtool.so")),
+            Some(PathBuf::from("lib/a/b/libtool.so")),
+        );
+        assert_eq!(filter.map(Path::new("tool-1.2.3/bin/sub/tool")), None);
+        assert_eq!(filter.map(Path::new("tool-1.2.3/lib/libtool.a")), None);
+        assert_eq!(filter.map(Path::new("tool-1.2.3/README")), None);
+        assert_eq!(filter.map(Path::new("tool-1.2.3")), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unpack_tar_with_filter() {
+        let archive = tar_archive(&[
+            (
+                "tool-1.2.3/bin/tool",
+                EntryType::Regular,
+                0o755,
+                "#!/bin/sh\n",
+            ),
+            ("tool-1.2.3/bin/alias", EntryType::Symlink, 0o777, "tool"),
+            (
+                "tool-1.2.3/bin/copy",
+                EntryType::Link,
+                0o755,
+                "tool-1.2.3/bin/tool",
+            ),
+            (
+                "tool-1.2.3/share/doc/README",
+                EntryType::Regular,
+                0o644,
+                "docs",
+            ),
+        ]);
+        let unpack_tar = |filter| {
+            let destination = tempfile::tempdir().unwrap();
+            let archive = tar::Archive::new(archive.as_slice());
+            unpack_with_limits(archive, destination.path(), &filter, unlimited())?;
+            io::Result::Ok(destination)
+        };
+
+        let destination = unpack_tar(EntryFilter::new(&["bin/*".to_owned()], 1).unwrap()).unwrap();
+        for path in ["bin/tool", "bin/alias", "bin/copy"] {
+            assert_eq!(
+                std::fs::read_to_string(destination.path().join(path)).unwrap(),
+                "#!/bin/sh\n",
+            );
+        }
+        assert!(!destination.path().join("share").exists());
+        assert!(!destination.path().join("tool-1.2.3").exists());
+
+        let err = unpack_tar(EntryFilter::new(&["bin/copy".to_owned()], 1).unwrap()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "archive entry `tool-1.2.3/bin/copy` links to `tool-1.2.3/bin/tool`, which is not extracted",
+        );
+    }
+
+    #[test]
+    fn unzip_with_filter() {
+        let zip_file = write_zip(&[
+            ("tool-1.2.3/bin/tool", 0o755, "#!/bin/sh\n"),
+            ("tool-1.2.3/README", 0o644, "hello\n"),
+        ]);
+        let destination = tempfile::tempdir().unwrap();
+        let filter = EntryFilter::new(&["bin/*".to_owned()], 1).unwrap();
+        unzip(zip_file.path(), destination.path(), &filter).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(destination.path().join("bin/tool")).unwrap(),
+            "#!/bin/sh\n",
+        );
+        assert!(!destination.path().join("README").exists());
+        assert!(!destination.path().join("tool-1.2.3").exists());
+    }
 }
@@ -7,28 +7,35 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::BufReader;
 use std::io::BufWriter;
 use std::path::Path;
 use std::path::PathBuf;
+use std::str::FromStr;
 
 use anyhow::format_err;
 use anyhow::Context as _;
+use bzip2::read::MultiBzDecoder;
 use serde_jsonrc::value::Value;
 use sha2::Digest as _;
 use sha2::Sha256;
 use tar::Archive;
+use xz2::read::XzDecoder;
 use zstd::stream::read::Decoder;
 
 use crate::artifact_location::ArtifactLocation;
+use crate::artifact_path::ArtifactPath;
 use crate::config::ArtifactEntry;
 use crate::config::HashAlgorithm;
 use crate::decompress;
+use crate::decompress::EntryFilter;
 use crate::digest::Digest;
 use crate::fetch_method::ArchiveFormat;
 use crate::fetch_method::ArtifactFormat;
 use crate::fetch_method::DecompressStep;
+use crate::linux_package;
 use crate::provider::ProviderFactory;
 #[cfg(unix)]
 use crate::util::chmodx::chmodx;
@@ -49,6 +56,7 @@ pub fn download_artifact<P: ProviderFactory>(
     artifact_entry: &ArtifactEntry,
     artifact_location: &ArtifactLocation,
     provider_factory: &P,
+    dotslash_file: &Path,
 ) -> anyhow::Result<()> {
     let artifact_parent_dir = artifact_location
         .artifact_directory
@@ -78,44 +86,121 @@ pub fn download_artifact<P: ProviderFactory>(
     let file_lock = acquire_download_lock_for_artifact(artifact_location)
         .context("failed to get artifact lock")?;
 
+    // The dictionary is only needed to unpack the artifact, so it is not
+    // kept in the cache.
+    let zstd_dictionary = match artifact_entry
+        .zstd
+        .as_ref()
+        .and_then(|zstd| zstd.dictionary.as_ref())
+    {
+        Some(dictionary) => {
+            let dictionary_entry = ArtifactEntry {
+                size: dictionary.size,
+                hash: dictionary.hash,
+                digest: dictionary.digest.clone(),
+                format: ArtifactFormat::Plain,
+                path: ArtifactPath::from_str("zstd-dictionary")?,
+                providers: dictionary.providers.clone(),
+                readonly: true,
+                zstd: None,
+                extract: vec![],
+                strip_components: 0,
+                executables: vec![],
+                symlinks: BTreeMap::new(),
+                interpreter: None,
+            };
+            let dictionary_destination = fs_ctx::namedtempfile_new_in(artifact_parent_dir)
+                .context("failed to create fetch temp path")?
+                .into_temp_path();
+            fetch_verified_artifact(
+                &dictionary_entry,
+                provider_factory,
+                &dictionary_destination,
+                &file_lock,
+                dotslash_file,
+            )
+            .map_err(|warnings| {
+                format_err!(
+                    "no providers succeeded for the zstd dictionary. warnings:\n{}",
+                    warnings.join("\n")
+                )
+            })?;
+            fs_ctx::read(&dictionary_destination)?
+        }
+        None => Vec::new(),
+    };
+
+    match fetch_verified_artifact(
+        artifact_entry,
+        provider_factory,
+        &fetch_destination,
+        &file_lock,
+        dotslash_file,
+    ) {
+        Ok(()) => {
+            unpack_verified_artifact(
+                &fetch_destination,
+                temp_dir_to_mv.path(),
+                artifact_entry,
+                &zstd_dictionary,
+            )?;
+            set_up_artifact_directory(temp_dir_to_mv.path(), artifact_entry)?;
+            if artifact_entry.readonly {
+                // Note that if we do `set_readonly(true)` on `temp_dir_to_mv.path()`,
+                // the `mv_no_clobber()` call fails.
+                make_tree_entries_read_only(temp_dir_to_mv.path())?;
+            }
+            mv_no_clobber(&temp_dir_to_mv, &artifact_location.artifact_directory)?;
+            Ok(())
+        }
+        Err(warnings) => Err(format_err!(
+            "no providers succeeded. warnings:\n{}",
+            warnings.join("\n")
+        )),
+    }
+}
+
+/// Tries each of the providers for `artifact_entry`, in order, until one
+/// writes a file to `destination` that matches the entry's size and digest.
+/// If none does, returns the reason each one failed.
+fn fetch_verified_artifact<P: ProviderFactory>(
+    artifact_entry: &ArtifactEntry,
+    provider_factory: &P,
+    destination: &Path,
+    file_lock: &FileLock,
+    dotslash_file: &Path,
+) -> Result<(), Vec<String>> {
     // Record warnings: only reported if no provider succeeds.
     let mut warnings = vec![];
     for provider_config in &artifact_entry.providers {
-        let provider_type = get_provider_type(provider_config)?;
-        let provider = provider_factory.get_provider(provider_type)?;
+        // A provider that this version of DotSlash does not understand is
+        // skipped rather than treated as fatal so that new types of providers
+        // can be added to a DotSlash file ahead of existing ones.
+        let provider = match get_provider_type(provider_config)
+            .and_then(|provider_type| provider_factory.get_provider(provider_type))
+        {
+            Ok(provider) => provider,
+            Err(e) => {
+                warnings.push(format!("skipped provider: {:?}", e));
+                continue;
+            }
+        };
         match provider.fetch_artifact(
             provider_config,
-            &fetch_destination,
-            &file_lock,
+            destination,
+            file_lock,
             artifact_entry,
+            dotslash_file,
         ) {
-            // If there is a failure, should we create a new temp_dir_to_mv?
-            Ok(_) => match verify_artifact(&fetch_destination, artifact_entry) {
-                Ok(_) => {
-                    unpack_verified_artifact(
-                        &fetch_destination,
-                        temp_dir_to_mv.path(),
-                        &artifact_entry.format,
-                        artifact_entry.path.as_str(),
-                    )?;
-                    if artifact_entry.readonly {
-                        // Note that if we do `set_readonly(true)` on `temp_dir_to_mv.path()`,
-                        // the `mv_no_clobber()` call fails.
-                        make_tree_entries_read_only(temp_dir_to_mv.path())?;
-                    }
-                    mv_no_clobber(&temp_dir_to_mv, &artifact_location.artifact_directory)?;
-                    return Ok(());
-                }
+            Ok(_) => match verify_artifact(destination, artifact_entry) {
+                Ok(_) => return Ok(()),
                 Err(e) => warnings.push(format!("warning: failed to verify artifact {:?}", e)),
             },
             Err(e) => warnings.push(format!("failed to fetch artifact: {:?}", e)),
         }
     }
 
-    Err(format_err!(
-        "no providers succeeded. warnings:\n{}",
-        warnings.join("\n")
-    ))
+    Err(warnings)
 }
 
 fn get_provider_type(provider_config: &Value) -> anyhow::Result<&str> {
@@ -190,25 +275,61 @@ fn verify_artifact(
 fn unpack_verified_artifact(
     fetched_artifact: &Path,
     temp_dir_to_mv: &Path,
-    format: &ArtifactFormat,
-    artifact_entry_path: &str,
+    artifact_entry: &ArtifactEntry,
+    zstd_dictionary: &[u8],
 ) -> anyhow::Result<()> {
-    match &format.extraction_policy() {
+    let zstd_window_log_max = artifact_entry
+        .zstd
+        .as_ref()
+        .and_then(|zstd| zstd.window_log_max);
+    let filter = EntryFilter::new(&artifact_entry.extract, artifact_entry.strip_components)?;
+    match &artifact_entry.format.extraction_policy() {
         (None, Some(ArchiveFormat::Tar)) => {
-            decompress::untar(fetched_artifact, temp_dir_to_mv, /* is_tar_gz */ false)?;
+            decompress::untar(
+                fetched_artifact,
+                temp_dir_to_mv,
+                /* is_tar_gz */ false,
+                &filter,
+            )?;
         }
         (Some(DecompressStep::Gzip), Some(ArchiveFormat::Tar)) => {
-            decompress::untar(fetched_artifact, temp_dir_to_mv, /* is_tar_gz */ true)?;
+            decompress::untar(
+                fetched_artifact,
+                temp_dir_to_mv,
+                /* is_tar_gz */ true,
+                &filter,
+            )?;
         }
         (Some(DecompressStep::Zstd), Some(ArchiveFormat::Tar)) => {
-            let zst_file = fs_ctx::file_open(fetched_artifact)?;
-            let reader = BufReader::new(zst_file);
-            let decoder = Decoder::new(reader)?;
+            let decoder = zstd_decoder(fetched_artifact, zstd_window_log_max, zstd_dictionary)?;
+            let archive = Archive::new(decoder);
+            decompress::unpack(archive, temp_dir_to_mv, &filter)?;
+        }
+        (Some(DecompressStep::Xz), Some(ArchiveFormat::Tar)) => {
+            let xz_file = fs_ctx::file_open(fetched_artifact)?;
+            let reader = BufReader::new(xz_file);
+            let decoder = XzDecoder::new_multi_decoder(reader);
             let archive = Archive::new(decoder);
-            decompress::unpack(archive, temp_dir_to_mv)?;
+            decompress::unpack(archive, temp_dir_to_mv, &filter)?;
+        }
+        (Some(DecompressStep::Bzip2), Some(ArchiveFormat::Tar)) => {
+            let bz2_file = fs_ctx::file_open(fetched_artifact)?;
+            let reader = BufReader::new(bz2_file);
+            let decoder = MultiBzDecoder::new(reader);
+            let archive = Archive::new(decoder);
+            decompress::unpack(archive, temp_dir_to_mv, &filter)?;
+        }
+        (_, Some(ArchiveFormat::Deb)) => {
+            linux_package::unpack_deb(fetched_artifact, temp_dir_to_mv, &filter)?;
+        }
+        (_, Some(ArchiveFormat::Rpm)) => {
+            linux_package::unpack_rpm(fetched_artifact, temp_dir_to_mv, &filter)?;
+        }
+        (_, Some(ArchiveFormat::Zip)) => {
+            decompress::unzip(fetched_artifact, temp_dir_to_mv, &filter)?;
         }
         (decompression, None) => {
-            let final_artifact_path = temp_dir_to_mv.join(artifact_entry_path);
+            let final_artifact_path = temp_dir_to_mv.join(artifact_entry.path.as_str());
             let parent = final_artifact_path.parent().unwrap();
             if parent != Path::new("") {
                 fs_ctx::create_dir_all(parent)?;
@@ -225,9 +346,26 @@ fn unpack_verified_artifact(
                 }
                 Some(DecompressStep::Zstd) => {
                     // fetched_artifact contains the .zst
-                    let zst_file = fs_ctx::file_open(fetched_artifact)?;
-                    let reader = BufReader::new(zst_file);
-                    let mut decoder = Decoder::new(reader)?;
+                    let mut decoder =
+                        zstd_decoder(fetched_artifact, zstd_window_log_max, zstd_dictionary)?;
+                    let output_file = fs_ctx::file_create(&final_artifact_path)?;
+                    let mut writer = BufWriter::new(output_file);
+                    std::io::copy(&mut decoder, &mut writer)?;
+                }
+                Some(DecompressStep::Xz) => {
+                    // fetched_artifact contains the .xz
+                    let xz_file = fs_ctx::file_open(fetched_artifact)?;
+                    let reader = BufReader::new(xz_file);
+                    let mut decoder = XzDecoder::new_multi_decoder(reader);
+                    let output_file = fs_ctx::file_create(&final_artifact_path)?;
+                    let mut writer = BufWriter::new(output_file);
+                    std::io::copy(&mut decoder, &mut writer)?;
+                }
+                Some(DecompressStep::Bzip2) => {
+                    // fetched_artifact contains the .bz2
+                    let bz2_file = fs_ctx::file_open(fetched_artifact)?;
+                    let reader = BufReader::new(bz2_file);
+                    let mut decoder = MultiBzDecoder::new(reader);
                     let output_file = fs_ctx::file_create(&final_artifact_path)?;
                     let mut writer = BufWriter::new(output_file);
                     std::io::copy(&mut decoder, &mut writer)?;
@@ -246,6 +384,111 @@ fn unpack_verified_artifact(
     Ok(())
 }
 
+/// Applies the `executables` and `symlinks` of the entry to the unpacked
+/// artifact in `temp_dir_to_mv`.
+fn set_up_artifact_directory(
+    temp_dir_to_mv: &Path,
+    artifact_entry: &ArtifactEntry,
+) -> anyhow::Result<()> {
+    if artifact_entry.executables.is_empty() && artifact_entry.symlinks.is_empty() {
+        return Ok(());
+    }
+    let artifact_dir = fs_ctx::canonicalize(temp_dir_to_mv)?;
+
+    let patterns = artifact_entry.executable_patterns()?;
+    let mut is_matched = vec![false; patterns.len()];
+    let mut files = Vec::new();
+    find_files(&artifact_dir, &mut files)?;
+    for file in files {
+        let relative_path = file.strip_prefix(&artifact_dir)?;
+        let mut is_executable = false;
+        for (pattern, is_matched) in patterns.iter().zip(&mut is_matched) {
+            if pattern.matches_path_with(relative_path, decompress::GLOB_MATCH_OPTIONS) {
+                *is_matched = true;
+                is_executable = true;
+            }
+        }
+        if is_executable {
+            make_executable(&file)?;
+        }
+    }
+    if let Some((pattern, _)) = artifact_entry
+        .executables
+        .iter()
+        .zip(is_matched)
+        .find(|(_, is_matched)| !is_matched)
+    {
+        return Err(format_err!(
+            "`executables` glob `{}` does not match any file",
+            pattern
+        ));
+    }
+
+    // Symlinks are created in order of their paths, so a symlink whose
+    // parent directory is another symlink is created after it.
+    for (link, target) in &artifact_entry.symlinks {
+        let path = artifact_dir.join(link.as_str());
+        let link_dir = path.parent().unwrap_or(&artifact_dir);
+        if !decompress::is_enclosed_link_target(&artifact_dir, link_dir, Path::new(target))? {
+            return Err(format_err!(
+                "symlink `{}` links to `{}`, which is outside of the artifact directory",
+                link,
+                target,
+            ));
+        }
+        decompress::symlink(target, &path)
+            .with_context(|| format!("failed to create symlink `{}`", link))?;
+    }
+
+    Ok(())
+}
+
+/// Like `chmod +x`, adds execute permission wherever there is read
+/// permission. Windows has no such permission, so it is a no-op there.
+fn make_executable(path: &Path) -> anyhow::Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt as _;
+        let mode = fs_ctx::metadata(path)?.permissions().mode();
+        decompress::set_unix_permissions(path, mode | (mode & 0o444) >> 2)?;
+    }
+    #[cfg(windows)]
+    let _ = path;
+    Ok(())
+}
+
+/// Collects the regular files under `dir`, without following symlinks.
+fn find_files(dir: &Path, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
+    for entry in fs_ctx::read_dir(dir)? {
+        let path = entry?.path();
+        let metadata = fs_ctx::symlink_metadata(&path)?;
+        if metadata.is_dir() {
+            find_files(&path, files)?;
+        } else if metadata.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn zstd_decoder(
+    fetched_artifact: &Path,
+    window_log_max: Option<u32>,
+    dictionary: &[u8],
+) -> anyhow::Result<Decoder<'static, BufReader<File>>> {
+    let zst_file = fs_ctx::file_open(fetched_artifact)?;
+    let reader = BufReader::new(zst_file);
+    // An empty dictionary is the same as no dictionary.
+    let mut decoder =
+        Decoder::with_dictionary(reader, dictionary).context("failed to load zstd dictionary")?;
+    if let Some(window_log_max) = window_log_max {
+        decoder
+            .window_log_max(window_log_max)
+            .with_context(|| format!("invalid zstd `window_log_max`: {}", window_log_max))?;
+    }
+    Ok(decoder)
+}
+
 /// Attempts to acquire an advisory lock for a lock file in the DotSlash cache
 /// that corresponds to the artifact specified by `scheme`, creating the file
 /// if necessary. This should be done before download_artifact() is called in
@@ -271,3 +514,248 @@ pub fn acquire_download_lock_for_artifact(
     }
     Ok(FileLock::default())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::Write as _;
+    use std::str::FromStr;
+
+    use serde_jsonrc::json;
+
+    use super::*;
+    use crate::config::ZstdDictionary;
+    use crate::config::ZstdOptions;
+    use crate::file_provider::FileProvider;
+    use crate::provider::Provider;
+
+    const CONTENTS: &str = "DotSlash Rulez!\n";
+
+    struct FakeProvider;
+
+    impl Provider for FakeProvider {
+        fn fetch_artifact(
+            &self,
+            _provider_config: &Value,
+            destination: &Path,
+            _fetch_lock: &FileLock,
+            _artifact_entry: &ArtifactEntry,
+            _dotslash_file: &Path,
+        ) -> anyhow::Result<()> {
+            fs::write(destination, CONTENTS)?;
+            Ok(())
+        }
+    }
+
+    struct FakeProviderFactory;
+
+    impl ProviderFactory for FakeProviderFactory {
+        fn get_provider(&self, provider_type: &str) -> anyhow::Result<Box<dyn Provider>> {
+            match provider_type {
+                "fake" => Ok(Box::new(FakeProvider)),
+                "file" => Ok(Box::new(FileProvider {})),
+                _ => Err(format_err!("unknown provider type: `{}`", provider_type)),
+            }
+        }
+    }
+
+    fn download(providers: Vec<Value>) -> anyhow::Result<PathBuf> {
+        download_entry(ArtifactEntry {
+            size: CONTENTS.len() as u64,
+            hash: HashAlgorithm::Sha256,
+            digest: Digest::try_from(
+                "52aa28f4f276bdd9a103fcd7f74f97f2bffc52dd816b887952f791b39356b08e".to_owned(),
+            )?,
+            format: ArtifactFormat::Plain,
+            path: ArtifactPath::from_str("my_tool")?,
+            providers,
+            readonly: true,
+            zstd: None,
+            extract: vec![],
+            strip_components: 0,
+            executables: vec![],
+            symlinks: BTreeMap::new(),
+            interpreter: None,
+        })
+    }
+
+    fn download_entry(artifact_entry: ArtifactEntry) -> anyhow::Result<PathBuf> {
+        let temp_dir = tempfile::tempdir()?.into_path();
+        let artifact_directory = temp_dir.join("ab").join("cdef");
+        let artifact_location = ArtifactLocation {
+            executable: artifact_directory.join(artifact_entry.path.as_str()),
+            artifact_directory,
+            lock_path: temp_dir.join("locks").join("abcdef"),
+        };
+        download_artifact(
+            &artifact_entry,
+            &artifact_location,
+            &FakeProviderFactory,
+            &temp_dir.join("my_tool"),
+        )?;
+        Ok(artifact_location.executable)
+    }
+
+    fn sha256(data: &[u8]) -> Digest {
+        Digest::try_from(format!("{:x}", Sha256::digest(data))).unwrap()
+    }
+
+    #[test]
+    fn skips_unknown_and_malformed_providers() -> anyhow::Result<()> {
+        let executable = download(vec![
+            json!({"type": "from-the-future"}),
+            json!({"type": 42}),
+            json!({"type": "fake"}),
+        ])?;
+        assert_eq!(fs::read_to_string(executable)?, CONTENTS);
+        Ok(())
+    }
+
+    #[test]
+    fn reports_skipped_providers_if_none_succeed() {
+        let err = download(vec![
+            json!({"type": "from-the-future"}),
+            json!({"type": 42}),
+        ])
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "no providers succeeded. warnings:\n\
+             skipped provider: unknown provider type: `from-the-future`\n\
+             skipped provider: type must map to a string",
+        );
+    }
+
+    #[test]
+    fn zstd_long_window_and_dictionary() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let dictionary = CONTENTS.repeat(8);
+        let dictionary_path = temp_dir.path().join("my_tool.dict");
+        fs::write(&dictionary_path, &dictionary)?;
+
+        // Like `zstd --long=28 -D my_tool.dict`.
+        let mut encoder =
+            zstd::stream::write::Encoder::with_dictionary(Vec::new(), 3, dictionary.as_bytes())?;
+        encoder.long_distance_matching(true)?;
+        encoder.window_log(28)?;
+        encoder.write_all(CONTENTS.as_bytes())?;
+        let compressed = encoder.finish()?;
+        let compressed_path = temp_dir.path().join("my_tool.zst");
+        fs::write(&compressed_path, &compressed)?;
+
+        let entry = |zstd| ArtifactEntry {
+            size: compressed.len() as u64,
+            hash: HashAlgorithm::Sha256,
+            digest: sha256(&compressed),
+            format: ArtifactFormat::Zstd,
+            path: ArtifactPath::from_str("my_tool").unwrap(),
+            providers: vec![json!({"type": "file", "path": compressed_path})],
+            readonly: true,
+            zstd,
+            extract: vec![],
+            strip_components: 0,
+            executables: vec![],
+            symlinks: BTreeMap::new(),
+            interpreter: None,
+        };
+        let dictionary_entry = || ZstdDictionary {
+            size: dictionary.len() as u64,
+            hash: HashAlgorithm::Sha256,
+            digest: sha256(dictionary.as_bytes()),
+            providers: vec![json!({"type": "file", "path": dictionary_path})],
+        };
+
+        let err = download_entry(entry(Some(ZstdOptions {
+            window_log_max: None,
+            dictionary: Some(dictionary_entry()),
+        })))
+        .unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("Frame requires too much memory"),
+            "{:#}",
+            err
+        );
+
+        let executable = download_entry(entry(Some(ZstdOptions {
+            window_log_max: Some(28),
+            dictionary: Some(dictionary_entry()),
+        })))?;
+        assert_eq!(fs::read_to_string(executable)?, CONTENTS);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn executables_and_symlinks() -> anyhow::Result<()> {
+        use std::os::unix::fs::PermissionsExt as _;
+
+        let set_up = |executables: &[&str], symlinks: &[(&str, &str)]| {
+            let temp_dir = tempfile::tempdir()?;
+            fs::create_dir_all(temp_dir.path().join("bin"))?;
+            for name in ["bin/clang", "README"] {
+                let path = temp_dir.path().join(name);
+                fs::write(&path, CONTENTS)?;
+                fs::set_permissions(&path, fs::Permissions::from_mode(0o644))?;
+            }
+            let artifact_entry = ArtifactEntry {
+                size: 0,
+                hash: HashAlgorithm::Sha256,
+                digest: sha256(b""),
+                format: ArtifactFormat::TarGz,
+                path: ArtifactPath::from_str("bin/clang++")?,
+                providers: vec![],
+                readonly: true,
+                zstd: None,
+                extract: vec![],
+                strip_components: 0,
+                executables: executables.iter().map(|glob| glob.to_string()).collect(),
+                symlinks: symlinks
+                    .iter()
+                    .map(|(link, target)| Ok((ArtifactPath::from_str(link)?, target.to_string())))
+                    .collect::<anyhow::Result<_>>()?,
+                interpreter: None,
+            };
+            set_up_artifact_directory(temp_dir.path(), &artifact_entry)?;
+            anyhow::Ok(temp_dir)
+        };
+
+        let temp_dir = set_up(
+            &["bin/*"],
+            &[("bin/clang++", "clang"), ("clang", "bin/clang")],
+        )?;
+        let mode = |name| {
+            anyhow::Ok(
+                fs::metadata(temp_dir.path().join(name))?
+                    .permissions()
+                    .mode(),
+            )
+        };
+        assert_eq!(mode("bin/clang")? & 0o777, 0o755);
+        assert_eq!(mode("README")? & 0o777, 0o644);
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("bin/clang++"))?,
+            CONTENTS
+        );
+        assert_eq!(fs::read_to_string(temp_dir.path().join("clang"))?, CONTENTS);
+
+        assert_eq!(
+            set_up(&["*/clang"], &[])?
+                .path()
+                .join("bin/clang")
+                .metadata()?
+                .permissions()
+                .mode()
+                & 0o777,
+            0o755,
+        );
+        assert_eq!(
+            set_up(&["lib/*"], &[]).unwrap_err().to_string(),
+            "`executables` glob `lib/*` does not match any file",
+        );
+        assert_eq!(
+            set_up(&[], &[("bin/clang++", "../../clang")]).unwrap_err().to_string(),
+            "symlink `bin/clang++` links to `../../clang`, which is outside of the artifact directory",
+        );
+        Ok(())
+    }
+}
@@ -7,10 +7,13 @@
  * of this source tree.
  */
 
+use std::env;
 use std::env::ArgsOs;
 use std::ffi::OsStr;
+use std::fmt;
 use std::fs;
 use std::io;
+use std::io::Read as _;
 use std::path::Path;
 use std::process::Command;
 use std::process::ExitCode;
@@ -22,7 +25,7 @@ use crate::artifact_location::determine_location;
 use crate::config;
 use crate::dotslash_cache::DotslashCache;
 use crate::download::download_artifact;
-use crate::platform::SUPPORTED_PLATFORM;
+use crate::platform;
 use crate::provider::ProviderFactory;
 use crate::subcommand::run_subcommand;
 use crate::subcommand::Subcommand;
@@ -56,6 +59,27 @@ pub fn run<P: ProviderFactory>(mut args: ArgsOs, provider_factory: &P) -> ExitCo
     ExitCode::FAILURE
 }
 
+/// Lists platform keys in order of preference, unlike `ListOf`.
+struct ExpectedPlatforms<'a>(&'a [String]);
+
+impl fmt::Display for ExpectedPlatforms<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            [platform] => write!(f, "platform `{}`", platform),
+            platforms => {
+                write!(f, "one of platforms ")?;
+                for (i, platform) in platforms.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "`{}`", platform)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
 fn run_dotslash_file<P: ProviderFactory>(
     file_arg: &OsStr,
     mut args: ArgsOs,
@@ -75,16 +99,33 @@ fn run_dotslash_file<P: ProviderFactory>(
         }
     };
 
-    let (_original_json, mut config_file) =
+    let (_original_json, config_file) =
         config::parse_file(&dotslash_data).context("failed to parse DotSlash file")?;
 
-    let (_platform, artifact_entry) = config_file
-        .platforms
-        .remove_entry(SUPPORTED_PLATFORM)
+    // The first of the host's platform keys with a valid entry wins. An
+    // entry that does not parse, such as one written for a newer DotSlash,
+    // is skipped so that a less preferred key can still be used.
+    let platform_keys = platform::platform_keys();
+    let mut artifact_entry = None;
+    let mut errors = Vec::new();
+    for platform in &platform_keys {
+        match config_file.artifact_entry(platform) {
+            Ok(Some(entry)) => {
+                artifact_entry = Some(entry);
+                break;
+            }
+            Ok(None) => {}
+            Err(err) => errors.push(format!("{:#}", err)),
+        }
+    }
+    if artifact_entry.is_none() && !errors.is_empty() {
+        return Err(format_err!("{}", errors.join("\n"))).context("failed to parse DotSlash file");
+    }
+    let artifact_entry = artifact_entry
         .ok_or_else(|| {
             format_err!(
-                "expected platform `{}` - but found {}",
-                SUPPORTED_PLATFORM,
+                "expected {} - but found {}",
+                ExpectedPlatforms(&platform_keys),
                 ListOf::new(config_file.platforms.keys()),
             )
         })
@@ -104,6 +145,54 @@ fn run_dotslash_file<P: ProviderFactory>(
     #[cfg(target_os = "linux")]
     update_artifact_mtime(&artifact_location.executable);
 
+    let dotslash_file = Path::new(file_arg);
+    let download = || {
+        download_artifact(
+            &artifact_entry,
+            &artifact_location,
+            provider_factory,
+            dotslash_file,
+        )
+        .with_context(|| {
+            format!(
+                "failed to download artifact into cache `{}` artifact location `{}`",
+                dotslash_cache.cache_dir().display(),
+                artifact_location.artifact_directory.display()
+            )
+        })?;
+
+        // Since we just unpacked the executable for the first time, we can
+        // afford to pay the macOS cost mentioned above.
+        #[cfg(unix)]
+        update_artifact_mtime(&artifact_location.executable);
+        anyhow::Ok(())
+    };
+
+    if let Some(interpreter) = &artifact_entry.interpreter {
+        // Running the interpreter says nothing about whether the artifact
+        // is in the cache, so check for it first.
+        if !artifact_location.executable.exists() {
+            download()?;
+        }
+        let mut command = interpreter_command(
+            interpreter.argv(),
+            &artifact_location.executable,
+            dotslash_file,
+        )?;
+        command.args(args);
+        let error = execv::execv(&mut command);
+        let program = Path::new(command.get_program());
+        let err_context = if is_file_not_found_error(&error) {
+            format!(
+                "failed to execute interpreter `{}` because it was not found",
+                program.display(),
+            )
+        } else {
+            format!("failed to execute interpreter `{}`", program.display())
+        };
+        return Err(error).context(err_context);
+    }
+
     let mut command = Command::new(&artifact_location.executable);
     command.args(args);
 
@@ -119,20 +208,7 @@ fn run_dotslash_file<P: ProviderFactory>(
         ));
     }
 
-    download_artifact(&artifact_entry, &artifact_location, provider_factory).with_context(
-        || {
-            format!(
-                "failed to download artifact into cache `{}` artifact location `{}`",
-                dotslash_cache.cache_dir().display(),
-                artifact_location.artifact_directory.display()
-            )
-        },
-    )?;
-
-    // Since we just unpacked the executable for the first time, we can
-    // afford to pay the macOS cost mentioned above.
-    #[cfg(unix)]
-    update_artifact_mtime(&artifact_location.executable);
+    download()?;
 
     // Now that we have fetched the artifact, try to execv again.
     let execv_error = execv::execv(&mut command);
@@ -162,6 +238,49 @@ fn run_dotslash_file<P: ProviderFactory>(
     Err(format_err!(execv_error).context(err_context))
 }
 
+/// Builds the command that runs `artifact` with the `interpreter` argv prefix.
+///
+/// A program name without a path separator is looked up in `$PATH`, while
+/// a path is relative to the directory of `dotslash_file`. If the program is
+/// itself a DotSlash file, it is run with this `dotslash` so that it works
+/// on Windows and does not depend on `dotslash` being on `$PATH`.
+fn interpreter_command(
+    interpreter: &[String],
+    artifact: &Path,
+    dotslash_file: &Path,
+) -> anyhow::Result<Command> {
+    let (program, interpreter_args) = interpreter
+        .split_first()
+        .ok_or_else(|| format_err!("`interpreter` must name a program"))?;
+    let is_path = program.contains('/') || (cfg!(windows) && program.contains('\\'));
+    let mut command = if !is_path {
+        Command::new(program)
+    } else {
+        let directory = match dotslash_file.parent() {
+            Some(parent) if parent != Path::new("") => parent,
+            _ => Path::new("."),
+        };
+        let program = directory.join(program);
+        if is_dotslash_file(&program) {
+            let dotslash = env::current_exe().context("failed to locate `dotslash`")?;
+            let mut command = Command::new(dotslash);
+            command.arg(program);
+            command
+        } else {
+            Command::new(program)
+        }
+    };
+    command.args(interpreter_args).arg(artifact);
+    Ok(command)
+}
+
+fn is_dotslash_file(path: &Path) -> bool {
+    let mut header = [0; config::REQUIRED_HEADER.len()];
+    fs::File::open(path)
+        .and_then(|mut file| file.read_exact(&mut header))
+        .is_ok_and(|()| header == config::REQUIRED_HEADER.as_bytes())
+}
+
 /// DotSlash can unpack old artifacts which can be reaped by tools like
 /// tmpwatch or tmpreaper. Those tools work better using the mtime rather than
 /// atime which is why we update the mtime. But this doesn't work on
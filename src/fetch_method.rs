@@ -17,40 +17,76 @@ pub enum ArtifactFormat {
     #[serde(skip)]
     Plain,
 
+    #[serde(rename = "bz2")]
+    Bz2,
+
+    #[serde(rename = "deb")]
+    Deb,
+
     #[serde(rename = "gz")]
     Gz,
 
+    #[serde(rename = "rpm")]
+    Rpm,
+
     #[serde(rename = "tar")]
     Tar,
 
+    #[serde(rename = "tar.bz2")]
+    TarBz2,
+
     #[serde(rename = "tar.gz")]
     TarGz,
 
+    #[serde(rename = "tar.xz")]
+    TarXz,
+
     #[serde(rename = "tar.zst")]
     TarZstd,
 
+    #[serde(rename = "xz")]
+    Xz,
+
+    #[serde(rename = "zip")]
+    Zip,
+
     #[serde(rename = "zst")]
     Zstd,
 }
 
 pub enum DecompressStep {
+    Bzip2,
     Gzip,
+    Xz,
     Zstd,
 }
 
-/// Currently, only .tar is supported, though we may support .zip in the future.
 pub enum ArchiveFormat {
+    /// Only the `data.tar` member of a .deb is unpacked.
+    Deb,
+    /// Only the cpio payload of an .rpm is unpacked.
+    Rpm,
     Tar,
+    /// Entries in a .zip are compressed individually, so a .zip is never
+    /// paired with a `DecompressStep`.
+    Zip,
 }
 
 impl ArtifactFormat {
     pub fn extraction_policy(&self) -> (Option<DecompressStep>, Option<ArchiveFormat>) {
         match self {
             Self::Plain => (None, None),
+            Self::Bz2 => (Some(DecompressStep::Bzip2), None),
+            Self::Deb => (None, Some(ArchiveFormat::Deb)),
             Self::Gz => (Some(DecompressStep::Gzip), None),
+            Self::Rpm => (None, Some(ArchiveFormat::Rpm)),
             Self::Tar => (None, Some(ArchiveFormat::Tar)),
+            Self::TarBz2 => (Some(DecompressStep::Bzip2), Some(ArchiveFormat::Tar)),
             Self::TarGz => (Some(DecompressStep::Gzip), Some(ArchiveFormat::Tar)),
+            Self::TarXz => (Some(DecompressStep::Xz), Some(ArchiveFormat::Tar)),
             Self::TarZstd => (Some(DecompressStep::Zstd), Some(ArchiveFormat::Tar)),
+            Self::Xz => (Some(DecompressStep::Xz), None),
+            Self::Zip => (None, Some(ArchiveFormat::Zip)),
             Self::Zstd => (Some(DecompressStep::Zstd), None),
         }
     }
@@ -0,0 +1,159 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use serde_jsonrc::value::Value;
+
+use crate::config::ArtifactEntry;
+use crate::provider::Provider;
+use crate::util::file_lock::FileLock;
+use crate::util::fs_ctx;
+
+/// Provider that reads the artifact from the local filesystem, which may be a
+/// network mount. Relative paths are resolved against the directory that
+/// contains the DotSlash file.
+pub struct FileProvider {}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct FileProviderConfig {
+    path: PathBuf,
+}
+
+impl Provider for FileProvider {
+    fn fetch_artifact(
+        &self,
+        provider_config: &Value,
+        destination: &Path,
+        _fetch_lock: &FileLock,
+        artifact_entry: &ArtifactEntry,
+        dotslash_file: &Path,
+    ) -> anyhow::Result<()> {
+        let FileProviderConfig { path } = FileProviderConfig::deserialize(provider_config)?;
+        let source = resolve_path(&path, dotslash_file);
+
+        // A hardlink is only safe if the fetched file is read and then
+        // discarded. For a single-file artifact, the fetched file is moved
+        // into the cache and has its permissions changed, which would also
+        // change the permissions of `source`.
+        let is_single_file = matches!(artifact_entry.format.extraction_policy(), (None, None));
+        if !is_single_file && fs::hard_link(&source, destination).is_ok() {
+            return Ok(());
+        }
+
+        fs_ctx::copy(&source, destination)
+            .with_context(|| format!("failed to fetch `{}`", source.display()))?;
+        Ok(())
+    }
+}
+
+fn resolve_path(path: &Path, dotslash_file: &Path) -> PathBuf {
+    match dotslash_file.parent() {
+        Some(parent) if path.is_relative() => parent.join(path),
+        _ => path.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn artifact_entry(format: Option<&str>) -> ArtifactEntry {
+        let mut entry = serde_jsonrc::json!({
+            "size": 16,
+            "hash": "sha256",
+            "digest": "52aa28f4f276bdd9a103fcd7f74f97f2bffc52dd816b887952f791b39356b08e",
+            "path": "my_tool",
+            "providers": [],
+        });
+        if let Some(format) = format {
+            entry["format"] = format.into();
+        }
+        serde_jsonrc::from_value(entry).unwrap()
+    }
+
+    #[test]
+    fn resolve_relative_path() {
+        assert_eq!(
+            resolve_path(
+                Path::new("third-party/my_tool"),
+                Path::new("repo/bin/my_tool")
+            ),
+            Path::new("repo/bin/third-party/my_tool"),
+        );
+        assert_eq!(
+            resolve_path(Path::new("my_tool.tar"), Path::new("my_tool")),
+            Path::new("my_tool.tar"),
+        );
+    }
+
+    #[test]
+    fn resolve_absolute_path() {
+        let absolute = std::env::temp_dir().join("my_tool");
+        assert_eq!(
+            resolve_path(&absolute, Path::new("repo/bin/my_tool")),
+            absolute
+        );
+    }
+
+    #[test]
+    fn fetch_relative_to_dotslash_file() -> anyhow::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let vendor_dir = temp_dir.path().join("third-party");
+        fs::create_dir(&vendor_dir)?;
+        fs::write(vendor_dir.join("my_tool"), "DotSlash Rulez!\n")?;
+        let dotslash_file = temp_dir.path().join("my_tool");
+        let destination = temp_dir.path().join("fetched");
+
+        for format in [None, Some("tar")] {
+            FileProvider {}.fetch_artifact(
+                &serde_jsonrc::json!({
+                    "type": "file",
+                    "path": "third-party/my_tool",
+                }),
+                &destination,
+                &FileLock::default(),
+                &artifact_entry(format),
+                &dotslash_file,
+            )?;
+            assert_eq!(fs::read_to_string(&destination)?, "DotSlash Rulez!\n");
+            fs::remove_file(&destination)?;
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn fetch_missing_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let err = FileProvider {}
+            .fetch_artifact(
+                &serde_jsonrc::json!({
+                    "type": "file",
+                    "path": "does-not-exist",
+                }),
+                &temp_dir.path().join("fetched"),
+                &FileLock::default(),
+                &artifact_entry(None),
+                &temp_dir.path().join("my_tool"),
+            )
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "failed to fetch `{}`",
+                temp_dir.path().join("does-not-exist").display(),
+            ),
+        );
+    }
+}
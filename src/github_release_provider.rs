@@ -7,24 +7,58 @@
  * of this source tree.
  */
 
-use std::fmt::Write;
+use std::env;
+use std::io;
 use std::path::Path;
 use std::process::Command;
+use std::process::Output;
 
+use anyhow::format_err;
+use anyhow::Context as _;
 use serde::Deserialize;
 use serde_jsonrc::value::Value;
 
 use crate::config::ArtifactEntry;
+use crate::http_client::FetchContext;
+use crate::http_client::HttpRequest;
 use crate::provider::Provider;
+use crate::url_rewrite::UrlRewrites;
+use crate::util::display::CommandDisplay;
+use crate::util::display::CommandStderrDisplay;
 use crate::util::file_lock::FileLock;
+use crate::util::fs_ctx;
+use crate::util::percent_encode::percent_encode;
 
+const DEFAULT_HOST: &str = "github.com";
+
+/// Environment variables that may hold a token for the REST API, in order of
+/// precedence. These are the same ones that `gh` reads.
+const TOKEN_ENV_VARS: &[&str] = &["GH_TOKEN", "GITHUB_TOKEN"];
+
+/// Fetches a release asset with `gh api`, so that the user's `gh auth`
+/// credentials are used. If `gh` is not installed, the GitHub REST API is
+/// used directly instead.
 pub struct GitHubReleaseProvider {}
 
 #[derive(Deserialize, Debug, PartialEq)]
 struct GitHubReleaseProviderConfig {
     tag: String,
+    /// `OWNER/REPO`, or `HOST/OWNER/REPO` for a GitHub Enterprise instance.
     repo: String,
     name: String,
+    /// Hostname of a GitHub Enterprise instance.
+    host: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Release {
+    assets: Vec<Asset>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Asset {
+    id: u64,
+    name: String,
 }
 
 impl Provider for GitHubReleaseProvider {
@@ -33,91 +67,278 @@ impl Provider for GitHubReleaseProvider {
         provider_config: &Value,
         destination: &Path,
         _fetch_lock: &FileLock,
-        _artifact_entry: &ArtifactEntry,
+        artifact_entry: &ArtifactEntry,
+        _dotslash_file: &Path,
     ) -> anyhow::Result<()> {
-        let GitHubReleaseProviderConfig { tag, repo, name } =
-            GitHubReleaseProviderConfig::deserialize(provider_config)?;
-        let _output = Command::new("gh")
-            .arg("release")
-            .arg("download")
-            .arg(tag)
-            .arg("--repo")
-            .arg(repo)
-            .arg("--pattern")
-            // We want to match an a release by name, but unfortunately,
-            // `gh release download` only supports --pattern, which takes a
-            // regex. Adding ^ and $ as anchors only seems to break things.
-            .arg(regex_escape(&name))
-            .arg("--output")
-            .arg(destination)
-            .output()?;
+        let config = GitHubReleaseProviderConfig::deserialize(provider_config)?;
+        let (host, repo) = host_and_repo(&config)?;
+
+        // Neither `gh` nor the REST API is likely to work where the URL
+        // rewrite rules point GitHub at a mirror, so use the mirror's copy of
+        // the asset's download URL, or at least its copy of the REST API.
+        let url_rewrites = UrlRewrites::load()?;
+        let download_url = download_url(host, repo, &config);
+        if url_rewrites.matches(&download_url) {
+            let fetch_context = FetchContext {
+                artifact_name: &config.name,
+                content_length: artifact_entry.size,
+                show_progress: false,
+            };
+            HttpRequest::new(&download_url)
+                .get_request(destination, &fetch_context)
+                .with_context(|| format!("failed to fetch `{}`", download_url))?;
+            return Ok(());
+        }
+
+        let release_path = format!(
+            "repos/{}/releases/tags/{}",
+            repo,
+            percent_encode(&config.tag, true),
+        );
+
+        if url_rewrites.matches(&api_url(host)) {
+            return fetch_with_rest_api(
+                host,
+                repo,
+                &release_path,
+                &config,
+                destination,
+                artifact_entry,
+            );
+        }
+        let mut gh_command = gh_api_command(host, &release_path);
+        let release = match gh_command.output() {
+            Ok(output) => check_gh_output(&gh_command, output)?.stdout,
+            // `gh` is not installed, so talk to the REST API directly.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                return fetch_with_rest_api(
+                    host,
+                    repo,
+                    &release_path,
+                    &config,
+                    destination,
+                    artifact_entry,
+                );
+            }
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("failed to execute `{}`", CommandDisplay::new(&gh_command))
+                });
+            }
+        };
+        let asset_id = find_asset_id(&parse_release(&release, &config.tag)?, &config.name)?;
+
+        let asset_path = format!("repos/{}/releases/assets/{}", repo, asset_id);
+        let mut gh_command = gh_api_command(host, &asset_path);
+        gh_command
+            .args(["--header", "Accept: application/octet-stream"])
+            .stdout(fs_ctx::file_create(destination)?);
+        let output = gh_command
+            .output()
+            .with_context(|| format!("failed to execute `{}`", CommandDisplay::new(&gh_command)))?;
+        check_gh_output(&gh_command, output)?;
         Ok(())
     }
 }
 
-/// We want the functionality comparable to regex::escape() without pulling in
-/// the entire crate.
-fn regex_escape(s: &str) -> String {
-    s.chars().fold(
-        // Releases filenames likely have at least one `.` in there that needs
-        // to be escaped, so add some padding, by default.
-        String::with_capacity(s.len() + 4),
-        |mut output, c| match c {
-            '\\' | '.' | '+' | '*' | '?' | '(' | ')' | '[' | ']' | '{' | '}' | '|' | '^' | '$' => {
-                let _ = write!(output, "\\{c}");
-                output
-            }
-            _ => {
-                let _ = write!(output, "{c}");
-                output
-            }
-        },
+fn fetch_with_rest_api(
+    host: &str,
+    repo: &str,
+    release_path: &str,
+    config: &GitHubReleaseProviderConfig,
+    destination: &Path,
+    artifact_entry: &ArtifactEntry,
+) -> anyhow::Result<()> {
+    let api_url = api_url(host);
+    let token = TOKEN_ENV_VARS
+        .iter()
+        .find_map(|var| env::var(var).ok().filter(|token| !token.is_empty()));
+
+    let release_url = format!("{}/{}", api_url, release_path);
+    let release = with_token(
+        HttpRequest::new(&release_url).header("Accept", "application/vnd.github+json"),
+        token.as_deref(),
+    )
+    .get_body()
+    .with_context(|| format!("failed to fetch `{}`", release_url))?;
+    let asset_id = find_asset_id(&parse_release(&release, &config.tag)?, &config.name)?;
+
+    let asset_url = format!("{}/repos/{}/releases/assets/{}", api_url, repo, asset_id);
+    let fetch_context = FetchContext {
+        artifact_name: &config.name,
+        content_length: artifact_entry.size,
+        show_progress: false,
+    };
+    with_token(
+        HttpRequest::new(&asset_url).header("Accept", "application/octet-stream"),
+        token.as_deref(),
+    )
+    .get_request(destination, &fetch_context)
+    .with_context(|| format!("failed to fetch `{}`", asset_url))?;
+    Ok(())
+}
+
+fn with_token<'a>(request: HttpRequest<'a>, token: Option<&str>) -> HttpRequest<'a> {
+    match token {
+        Some(token) => request.header("Authorization", format!("Bearer {}", token)),
+        None => request,
+    }
+}
+
+fn gh_api_command(host: &str, path: &str) -> Command {
+    let mut command = Command::new("gh");
+    command.args(["api", "--hostname", host, path]);
+    command
+}
+
+fn check_gh_output(command: &Command, output: Output) -> anyhow::Result<Output> {
+    if output.status.success() {
+        Ok(output)
+    } else {
+        Err(format_err!("{}", CommandStderrDisplay::new(&output)))
+            .with_context(|| format!("`{}`", CommandDisplay::new(command)))
+    }
+}
+
+/// Splits the host from `repo` if it was written as `HOST/OWNER/REPO`, which
+/// is what `gh --repo` accepts.
+fn host_and_repo(config: &GitHubReleaseProviderConfig) -> anyhow::Result<(&str, &str)> {
+    let parts = config.repo.split('/').collect::<Vec<_>>();
+    if parts.iter().any(|part| part.is_empty()) {
+        return Err(format_err!("invalid `repo` `{}`", config.repo));
+    }
+    match (parts.len(), &config.host) {
+        (2, host) => Ok((host.as_deref().unwrap_or(DEFAULT_HOST), &config.repo)),
+        (3, None) => Ok(config.repo.split_once('/').unwrap()),
+        (3, Some(_)) => Err(format_err!(
+            "`repo` `{}` includes a host, so `host` must not be specified",
+            config.repo,
+        )),
+        _ => Err(format_err!(
+            "`repo` must be of the form `OWNER/REPO`, but was `{}`",
+            config.repo,
+        )),
+    }
+}
+
+/// The URL that the web UI links to, which redirects to the asset.
+fn download_url(host: &str, repo: &str, config: &GitHubReleaseProviderConfig) -> String {
+    format!(
+        "https://{}/{}/releases/download/{}/{}",
+        host,
+        repo,
+        percent_encode(&config.tag, true),
+        percent_encode(&config.name, true),
     )
 }
 
+fn api_url(host: &str) -> String {
+    if host == DEFAULT_HOST {
+        "https://api.github.com".to_owned()
+    } else {
+        format!("https://{}/api/v3", host)
+    }
+}
+
+fn parse_release(json: &[u8], tag: &str) -> anyhow::Result<Release> {
+    serde_jsonrc::from_slice(json).with_context(|| format!("failed to parse release `{}`", tag))
+}
+
+fn find_asset_id(release: &Release, name: &str) -> anyhow::Result<u64> {
+    release
+        .assets
+        .iter()
+        .find(|asset| asset.name == name)
+        .map(|asset| asset.id)
+        .with_context(|| format!("release has no asset named `{}`", name))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn config(repo: &str, host: Option<&str>) -> GitHubReleaseProviderConfig {
+        GitHubReleaseProviderConfig {
+            tag: "v0.12.0".to_owned(),
+            repo: repo.to_owned(),
+            name: "hermes-cli-linux-v0.12.0.tar.gz".to_owned(),
+            host: host.map(ToOwned::to_owned),
+        }
+    }
+
     #[test]
-    fn regex_escape_no_quotable_chars() {
-        assert_eq!("foo", regex_escape("foo"));
-        assert_eq!("FOO", regex_escape("FOO"));
-        // Spaces do not get escaped.
-        assert_eq!("foo bar", regex_escape("foo bar"));
-        // Angle brackets do not get escaped.
-        assert_eq!("<abc>", regex_escape("<abc>"));
-        // Slashes do not get escaped.
-        assert_eq!("foo/bar", regex_escape("foo/bar"));
+    fn host_and_repo_defaults_to_github() {
+        assert_eq!(
+            host_and_repo(&config("facebook/hermes", None)).unwrap(),
+            ("github.com", "facebook/hermes"),
+        );
     }
 
     #[test]
-    fn regex_escape_punctuation() {
-        assert_eq!("abc\\.tar\\.gz", regex_escape("abc.tar.gz"));
-        assert_eq!("what\\?!\\?", regex_escape("what?!?"));
+    fn host_and_repo_enterprise() {
+        assert_eq!(
+            host_and_repo(&config("facebook/hermes", Some("ghe.example.com"))).unwrap(),
+            ("ghe.example.com", "facebook/hermes"),
+        );
+        assert_eq!(
+            host_and_repo(&config("ghe.example.com/facebook/hermes", None)).unwrap(),
+            ("ghe.example.com", "facebook/hermes"),
+        );
+        assert!(host_and_repo(&config(
+            "ghe.example.com/facebook/hermes",
+            Some("github.com")
+        ))
+        .is_err());
     }
 
     #[test]
-    fn regex_escape_brackets() {
-        assert_eq!("\\[abc\\]", regex_escape("[abc]"));
-        assert_eq!("\\{abc\\}", regex_escape("{abc}"));
-        assert_eq!("\\(abc\\)", regex_escape("(abc)"));
+    fn host_and_repo_invalid() {
+        assert_eq!(
+            host_and_repo(&config("hermes", None))
+                .unwrap_err()
+                .to_string(),
+            "`repo` must be of the form `OWNER/REPO`, but was `hermes`",
+        );
+        assert!(host_and_repo(&config("facebook/", None)).is_err());
     }
 
     #[test]
-    fn regex_escape_anchors() {
-        assert_eq!("\\^foobarbaz\\$", regex_escape("^foobarbaz$"));
+    fn test_download_url() {
+        assert_eq!(
+            download_url("github.com", "facebook/hermes", &config("facebook/hermes", None)),
+            "https://github.com/facebook/hermes/releases/download/v0.12.0/hermes-cli-linux-v0.12.0.tar.gz",
+        );
     }
 
     #[test]
-    fn regex_escape_quantifiers() {
-        assert_eq!("https\\?://", regex_escape("https?://"));
-        assert_eq!("foo\\+foo\\+", regex_escape("foo+foo+"));
-        assert_eq!("foo\\*foo\\*", regex_escape("foo*foo*"));
+    fn test_api_url() {
+        assert_eq!(api_url("github.com"), "https://api.github.com");
+        assert_eq!(api_url("ghe.example.com"), "https://ghe.example.com/api/v3");
     }
 
     #[test]
-    fn regex_escape_alternation() {
-        assert_eq!("foo\\|bar", regex_escape("foo|bar"));
+    fn find_asset_id_matches_name_exactly() {
+        let release = parse_release(
+            br#"{
+                "tag_name": "v0.12.0",
+                "assets": [
+                    {"id": 1, "name": "hermes-cli-linux-v0.12.0.tar.gz.sha256"},
+                    {"id": 2, "name": "hermes-cli-linuxXv0.12.0.tar.gz"},
+                    {"id": 3, "name": "hermes-cli-linux-v0.12.0.tar.gz"}
+                ]
+            }"#,
+            "v0.12.0",
+        )
+        .unwrap();
+        assert_eq!(
+            find_asset_id(&release, "hermes-cli-linux-v0.12.0.tar.gz").unwrap(),
+            3,
+        );
+        assert_eq!(
+            find_asset_id(&release, "hermes-cli-linux")
+                .unwrap_err()
+                .to_string(),
+            "release has no asset named `hermes-cli-linux`",
+        );
     }
 }
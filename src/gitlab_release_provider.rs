@@ -0,0 +1,206 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::env;
+use std::path::Path;
+
+use anyhow::format_err;
+use anyhow::Context as _;
+use serde::Deserialize;
+use serde_jsonrc::value::Value;
+
+use crate::config::ArtifactEntry;
+use crate::http_client::origin;
+use crate::http_client::FetchContext;
+use crate::http_client::HttpRequest;
+use crate::provider::Provider;
+use crate::util::file_lock::FileLock;
+use crate::util::percent_encode::percent_encode;
+
+const DEFAULT_BASE_URL: &str = "https://gitlab.com";
+
+/// Fetches an asset attached to a GitLab release, or a file from a project's
+/// generic package registry, through the GitLab REST API.
+///
+/// https://docs.gitlab.com/ee/api/releases/
+/// https://docs.gitlab.com/ee/user/packages/generic_packages/
+pub struct GitLabReleaseProvider {}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct GitLabReleaseProviderConfig {
+    /// Numeric ID or full path (e.g., `group/subgroup/project`).
+    project: String,
+    /// Name of the release asset link, or of the file in the package.
+    name: String,
+    tag: Option<String>,
+    package: Option<String>,
+    version: Option<String>,
+    /// Base URL of a self-hosted instance, e.g. `https://gitlab.example.com`.
+    base_url: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Release {
+    assets: ReleaseAssets,
+}
+
+#[derive(Deserialize, Debug)]
+struct ReleaseAssets {
+    #[serde(default)]
+    links: Vec<ReleaseLink>,
+}
+
+#[derive(Deserialize, Debug)]
+struct ReleaseLink {
+    name: String,
+    url: String,
+    direct_asset_url: Option<String>,
+}
+
+impl Provider for GitLabReleaseProvider {
+    fn fetch_artifact(
+        &self,
+        provider_config: &Value,
+        destination: &Path,
+        _fetch_lock: &FileLock,
+        artifact_entry: &ArtifactEntry,
+        _dotslash_file: &Path,
+    ) -> anyhow::Result<()> {
+        let config = GitLabReleaseProviderConfig::deserialize(provider_config)?;
+        let base_url = config
+            .base_url
+            .as_deref()
+            .unwrap_or(DEFAULT_BASE_URL)
+            .trim_end_matches('/');
+        let project_url = format!(
+            "{}/api/v4/projects/{}",
+            base_url,
+            percent_encode(&config.project, true),
+        );
+        let token = token_header();
+
+        let url = match (&config.tag, &config.package, &config.version) {
+            (Some(tag), None, None) => {
+                let release_url = format!("{}/releases/{}", project_url, percent_encode(tag, true));
+                let body = with_token(HttpRequest::new(&release_url), token.as_ref())
+                    .get_body()
+                    .with_context(|| format!("failed to fetch release `{}`", tag))?;
+                let release = serde_jsonrc::from_slice::<Release>(&body)
+                    .with_context(|| format!("failed to parse release `{}`", tag))?;
+                find_link_url(&release, &config.name)?.to_owned()
+            }
+            (None, Some(package), Some(version)) => format!(
+                "{}/packages/generic/{}/{}/{}",
+                project_url,
+                percent_encode(package, true),
+                percent_encode(version, true),
+                percent_encode(&config.name, true),
+            ),
+            _ => {
+                return Err(format_err!(
+                    "either `tag` or both `package` and `version` must be specified"
+                ));
+            }
+        };
+
+        // Release links may point anywhere, so only send the token to the
+        // GitLab instance itself. The `HttpBackend` drops it if GitLab then
+        // redirects to object storage on another host.
+        let token = token.filter(|_| origin(&url) == origin(base_url));
+        let fetch_context = FetchContext {
+            artifact_name: &config.name,
+            content_length: artifact_entry.size,
+            show_progress: false,
+        };
+        with_token(HttpRequest::new(&url), token.as_ref())
+            .get_request(destination, &fetch_context)
+            .with_context(|| format!("failed to fetch `{}`", url))?;
+        Ok(())
+    }
+}
+
+/// A personal, project, or group access token from `GITLAB_TOKEN` takes
+/// precedence over the `CI_JOB_TOKEN` that GitLab CI sets for each job.
+fn token_header() -> Option<(&'static str, String)> {
+    let from_env = |var| {
+        env::var(var)
+            .ok()
+            .filter(|token: &String| !token.is_empty())
+    };
+    from_env("GITLAB_TOKEN")
+        .map(|token| ("PRIVATE-TOKEN", token))
+        .or_else(|| from_env("CI_JOB_TOKEN").map(|token| ("JOB-TOKEN", token)))
+}
+
+fn with_token<'a>(request: HttpRequest<'a>, token: Option<&(&str, String)>) -> HttpRequest<'a> {
+    match token {
+        Some((name, token)) => request.header(*name, token),
+        None => request,
+    }
+}
+
+fn find_link_url<'a>(release: &'a Release, name: &str) -> anyhow::Result<&'a str> {
+    release
+        .assets
+        .links
+        .iter()
+        .find(|link| link.name == name)
+        .map(|link| link.direct_asset_url.as_deref().unwrap_or(&link.url))
+        .with_context(|| format!("release has no asset link named `{}`", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_link_url_by_exact_name() {
+        let release = serde_jsonrc::from_str::<Release>(
+            r#"{
+                "tag_name": "v1.2.3",
+                "assets": {
+                    "count": 3,
+                    "sources": [],
+                    "links": [
+                        {
+                            "id": 1,
+                            "name": "tool-linux.tar.gz.sha256",
+                            "url": "https://example.com/tool-linux.tar.gz.sha256",
+                            "direct_asset_url": "https://gitlab.com/group/tool/-/releases/v1.2.3/downloads/tool-linux.tar.gz.sha256"
+                        },
+                        {
+                            "id": 2,
+                            "name": "tool-linux.tar.gz",
+                            "url": "https://example.com/tool-linux.tar.gz",
+                            "direct_asset_url": "https://gitlab.com/group/tool/-/releases/v1.2.3/downloads/tool-linux.tar.gz"
+                        },
+                        {
+                            "id": 3,
+                            "name": "tool-macos.tar.gz",
+                            "url": "https://example.com/tool-macos.tar.gz"
+                        }
+                    ]
+                }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(
+            find_link_url(&release, "tool-linux.tar.gz").unwrap(),
+            "https://gitlab.com/group/tool/-/releases/v1.2.3/downloads/tool-linux.tar.gz",
+        );
+        assert_eq!(
+            find_link_url(&release, "tool-macos.tar.gz").unwrap(),
+            "https://example.com/tool-macos.tar.gz",
+        );
+        assert_eq!(
+            find_link_url(&release, "tool").unwrap_err().to_string(),
+            "release has no asset link named `tool`",
+        );
+    }
+}
@@ -0,0 +1,461 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! HTTP GET requests, made either by running `curl` or, when DotSlash is
+//! built with the `native-http` feature, in-process.
+
+use std::env;
+use std::fmt;
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::credentials::authorization_for;
+use crate::credentials::CredentialsError;
+use crate::curl::CurlBackend;
+use crate::curl::CurlError;
+#[cfg(feature = "native-http")]
+use crate::native_http::NativeBackend;
+#[cfg(feature = "native-http")]
+use crate::native_http::NativeHttpError;
+use crate::progress::display_progress;
+use crate::url_rewrite::UrlRewriteError;
+use crate::url_rewrite::UrlRewrites;
+
+/// Selects the backend used to make requests: `curl` (the default) or
+/// `native`.
+pub const HTTP_BACKEND_ENV_VAR: &str = "DOTSLASH_HTTP_BACKEND";
+
+const NUM_RETRYABLE_MAX_ATTEMPTS: u8 = 3;
+
+/// Number of times a backend should retry a transient error (a timeout or a
+/// 408, 429, 500, 502, 503 or 504 response) on its own.
+pub const NUM_TRANSIENT_ERROR_MAX_ATTEMPTS: u64 = 3;
+
+/// Same as curl's default for `--max-redirs`.
+pub const MAX_REDIRECTS: u32 = 50;
+
+/// Specify a custom user-agent when making requests. In the unfortunate event
+/// that a site hosting an artifact gets overloaded with requests, hopefully
+/// this will help them identify whether DotSlash is involed.
+pub const USER_AGENT: &str = concat!(
+    "Mozilla/5.0 (compatible; DotSlash/",
+    env!("CARGO_PKG_VERSION"),
+    "; +",
+    env!("CARGO_PKG_HOMEPAGE"),
+    ")"
+);
+
+pub struct FetchContext<'a> {
+    pub artifact_name: &'a str,
+    pub content_length: u64,
+    pub show_progress: bool,
+}
+
+#[derive(Debug, Error)]
+pub enum HttpError {
+    #[error(transparent)]
+    Curl(Box<CurlError>),
+
+    #[cfg(feature = "native-http")]
+    #[error(transparent)]
+    Native(#[from] NativeHttpError),
+
+    #[error(
+        "unknown HTTP backend `{0}` in DOTSLASH_HTTP_BACKEND (supported backends: {})",
+        SUPPORTED_BACKENDS
+    )]
+    UnknownBackend(String),
+
+    #[error(transparent)]
+    Credentials(#[from] CredentialsError),
+
+    #[error(transparent)]
+    UrlRewrite(#[from] UrlRewriteError),
+
+    // Only the name is reported because the value may be a secret.
+    #[error("header `{0}` has an invalid name or value")]
+    InvalidHeader(String),
+
+    // DotSlash failed managing the thread responsible for displaying a progress
+    // indicator.
+    #[error("progress indicator thread panicked with `{0}`")]
+    JoinProgressThread(String),
+}
+
+#[cfg(feature = "native-http")]
+const SUPPORTED_BACKENDS: &str = "curl, native";
+#[cfg(not(feature = "native-http"))]
+const SUPPORTED_BACKENDS: &str = "curl";
+
+impl From<CurlError> for HttpError {
+    fn from(error: CurlError) -> HttpError {
+        HttpError::Curl(Box::new(error))
+    }
+}
+
+impl HttpError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            Self::Curl(e) => e.is_retryable(),
+            #[cfg(feature = "native-http")]
+            Self::Native(e) => e.is_retryable(),
+            _ => false,
+        }
+    }
+
+    fn is_too_many_requests(&self) -> bool {
+        match self {
+            Self::Curl(e) => e.is_too_many_requests(),
+            #[cfg(feature = "native-http")]
+            Self::Native(e) => e.is_too_many_requests(),
+            _ => false,
+        }
+    }
+}
+
+/// A single attempt at a GET request. Implementations are expected to follow
+/// up to `MAX_REDIRECTS` redirects, send `USER_AGENT`, and retry transient
+/// errors up to `NUM_TRANSIENT_ERROR_MAX_ATTEMPTS` times. Other retries are
+/// handled by `HttpRequest`.
+///
+/// Because the headers of a request may carry credentials, none of them may be
+/// sent after a redirect to a different `origin()`, e.g., from a GitLab
+/// instance to the object storage that serves its packages.
+pub trait HttpBackend {
+    /// Writes the response body to `target`. The file should be written to
+    /// as the body is received so that the progress indicator can poll it.
+    fn get_to_file(&self, request: &HttpRequest, target: &Path) -> Result<(), HttpError>;
+
+    /// Returns the response body.
+    fn get_body(&self, request: &HttpRequest) -> Result<Vec<u8>, HttpError>;
+
+    /// Returns the status code and headers of the final response, even if the
+    /// status code indicates an error.
+    fn get_headers(&self, request: &HttpRequest)
+        -> Result<(u16, Vec<(String, String)>), HttpError>;
+}
+
+/// Header values are treated as secrets because they often carry credentials:
+/// they are not part of the `Debug` output, and backends must not include them
+/// in error messages or in the arguments of a spawned process.
+#[derive(Clone)]
+pub struct HttpRequest<'a> {
+    pub url: &'a str,
+    pub headers: Vec<(String, String)>,
+}
+
+impl fmt::Debug for HttpRequest<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HttpRequest")
+            .field("url", &self.url)
+            .field(
+                "headers",
+                &self
+                    .headers
+                    .iter()
+                    .map(|(name, _)| (name, "<redacted>"))
+                    .collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl HttpRequest<'_> {
+    pub fn new(url: &str) -> HttpRequest<'_> {
+        HttpRequest {
+            url,
+            headers: Vec::new(),
+        }
+    }
+
+    /// Adds a header to send with the request.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn get_request(&self, target: &Path, context: &FetchContext) -> Result<(), HttpError> {
+        // While making the request, poll the target and report what percentage
+        // done it is compared to content_length.
+        let handler = if context.show_progress {
+            eprintln!("Downloading {}...", context.artifact_name);
+            Some(display_progress(context.content_length, target))
+        } else {
+            None
+        };
+
+        // If the request fails, the `progress_sender` channel is dropped,
+        // and the progress thread uses this to finish by itself.
+        self.send(|backend, request| backend.get_to_file(request, target))?;
+
+        if let Some((progress_sender, join_handler)) = handler {
+            // Let the progress thread know that we're done done.
+            // It may already know this if the content size information
+            // is correct.
+            let _ = progress_sender.send(());
+            join_handler
+                .join()
+                .map_err(|e| HttpError::JoinProgressThread(format!("{:?}", e)))?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the body of the response.
+    pub fn get_body(&self) -> Result<Vec<u8>, HttpError> {
+        self.send(|backend, request| backend.get_body(request))
+    }
+
+    /// Returns the status code and the headers of the response, even if the
+    /// status code indicates an error. If there were redirects, only the final
+    /// response is considered.
+    pub fn get_headers(&self) -> Result<(u16, Vec<(String, String)>), HttpError> {
+        self.send(|backend, request| backend.get_headers(request))
+    }
+
+    /// Makes the request with retries, at each of the URLs that the URL
+    /// rewrite rules give for `self.url` until one succeeds. As after a
+    /// redirect, the headers are only sent to a URL with the same `origin()`.
+    fn send<T>(
+        &self,
+        mut get: impl FnMut(&dyn HttpBackend, &HttpRequest) -> Result<T, HttpError>,
+    ) -> Result<T, HttpError> {
+        let urls = UrlRewrites::load()?.apply(self.url);
+        let mut attempt = |url: &str| {
+            let headers = if origin(url) == origin(self.url) {
+                self.headers.clone()
+            } else {
+                Vec::new()
+            };
+            let request = HttpRequest { url, headers };
+            let request = request.with_credentials()?;
+            let backend = request.backend()?;
+            with_retries(|| get(&*backend, &request))
+        };
+        let mut result = attempt(&urls[0]);
+        for url in &urls[1..] {
+            if result.is_ok() {
+                break;
+            }
+            result = attempt(url);
+        }
+        result
+    }
+
+    /// Adds an `Authorization` header from the credential helper or the
+    /// netrc file, unless the request already has one.
+    fn with_credentials(&self) -> Result<HttpRequest<'_>, HttpError> {
+        let mut request = self.clone();
+        let has_authorization = self
+            .headers
+            .iter()
+            .any(|(name, _)| name.eq_ignore_ascii_case("authorization"));
+        if !has_authorization {
+            if let Some(authorization) = authorization_for(self.url)? {
+                request
+                    .headers
+                    .push(("Authorization".to_owned(), authorization));
+            }
+        }
+        Ok(request)
+    }
+
+    /// Validates the headers up front so that a backend never gets to echo
+    /// an invalid one back in an error.
+    fn backend(&self) -> Result<Box<dyn HttpBackend>, HttpError> {
+        if let Some((name, _)) = self
+            .headers
+            .iter()
+            .find(|(name, value)| !is_valid_header(name, value))
+        {
+            return Err(HttpError::InvalidHeader(name.clone()));
+        }
+
+        match env::var(HTTP_BACKEND_ENV_VAR).as_deref() {
+            Err(_) | Ok("") | Ok("curl") => Ok(Box::new(CurlBackend)),
+            #[cfg(feature = "native-http")]
+            Ok("native") => Ok(Box::new(NativeBackend)),
+            Ok(name) => Err(HttpError::UnknownBackend(name.to_owned())),
+        }
+    }
+}
+
+/// Returns the `scheme://host:port` part of `url`.
+pub fn origin(url: &str) -> &str {
+    let start = url.find("://").map_or(0, |i| i + 3);
+    match url[start..].find(['/', '?', '#']) {
+        Some(end) => &url[..start + end],
+        None => url,
+    }
+}
+
+/// https://www.rfc-editor.org/rfc/rfc9110#section-5
+fn is_valid_header(name: &str, value: &str) -> bool {
+    let is_tchar = |b: u8| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b);
+    !name.is_empty()
+        && name.bytes().all(is_tchar)
+        && value
+            .bytes()
+            .all(|b| b == b'\t' || (b >= b' ' && b != 0x7f))
+}
+
+fn with_retries<T>(mut request: impl FnMut() -> Result<T, HttpError>) -> Result<T, HttpError> {
+    let mut retries = 1..=NUM_RETRYABLE_MAX_ATTEMPTS;
+    loop {
+        let error = match request() {
+            Ok(value) => return Ok(value),
+            Err(error) => error,
+        };
+
+        // Sometimes we manually retry ourselves...
+        if let Some(retry_num) = retries.next() {
+            // The request failed, but it satisfies our "retryable" heuristic,
+            // so loop again.
+            if error.is_retryable() {
+                continue;
+            }
+
+            // The request failed, but we're hitting the server too hard,
+            // so loop again, but wait a little bit.
+            if error.is_too_many_requests() {
+                // 1^3=1s  ->  2^3=8s  ->  3^3=27s
+                thread::sleep(Duration::from_secs(retry_num.pow(3) as u64));
+                continue;
+            }
+        }
+
+        return Err(error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::BufRead as _;
+    use std::io::BufReader;
+    use std::io::Write as _;
+    use std::net::TcpListener;
+    use std::thread::JoinHandle;
+
+    use super::*;
+
+    /// Answers one connection per response and returns the requests.
+    fn serve(responses: Vec<String>) -> (u16, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = String::new();
+                let mut reader = BufReader::new(&mut stream);
+                while reader.read_line(&mut request).unwrap() > 2 {}
+                stream.write_all(response.as_bytes()).unwrap();
+                requests.push(request);
+            }
+            requests
+        });
+        (port, handle)
+    }
+
+    fn redirect(location: &str) -> String {
+        format!(
+            "HTTP/1.1 302 Found\r\nLocation: {location}\r\n\
+             Content-Length: 0\r\nConnection: close\r\n\r\n"
+        )
+    }
+
+    fn assert_headers_dropped_across_origins(backend: &dyn HttpBackend) {
+        let (storage_port, storage) = serve(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok".to_owned(),
+        ]);
+        let (port, server) = serve(vec![
+            redirect("/moved"),
+            redirect(&format!("http://127.0.0.1:{storage_port}/tool.tar.gz")),
+        ]);
+        let url = format!("http://127.0.0.1:{port}/tool.tar.gz");
+        let request = HttpRequest::new(&url)
+            .header("Authorization", "Bearer s3cr3t")
+            .header("X-JFrog-Art-Api", "s3cr3t");
+
+        assert_eq!(backend.get_body(&request).unwrap(), b"ok");
+        let requests = server.join().unwrap();
+        assert!(requests[0].starts_with("GET /tool.tar.gz "));
+        assert!(requests[1].starts_with("GET /moved "));
+        for request in requests {
+            assert_eq!(request.matches("s3cr3t").count(), 2, "{request}");
+        }
+        let requests = storage.join().unwrap();
+        assert!(!requests[0].contains("s3cr3t"), "{}", requests[0]);
+    }
+
+    #[test]
+    fn curl_drops_headers_across_origins() {
+        assert_headers_dropped_across_origins(&CurlBackend);
+    }
+
+    #[cfg(feature = "native-http")]
+    #[test]
+    fn native_drops_headers_across_origins() {
+        assert_headers_dropped_across_origins(&NativeBackend);
+    }
+
+    #[test]
+    fn user_agent() {
+        let version = env!("CARGO_PKG_VERSION");
+        // For reference, the user-agent that Google sends from its webcrawler is:
+        //
+        //     Mozilla/5.0 (compatible; Googlebot/2.1; +http://www.google.com/bot.html)
+        //
+        // The purpose of this test is to make it easier to visually verify that
+        // the user-agent is what we expect.
+        assert_eq!(
+            format!("Mozilla/5.0 (compatible; DotSlash/{version}; +https://dotslash-cli.com)"),
+            USER_AGENT
+        );
+    }
+
+    #[test]
+    fn test_origin() {
+        assert_eq!(
+            origin("https://gitlab.com/api/v4/projects/1"),
+            "https://gitlab.com"
+        );
+        assert_eq!(origin("http://localhost:8080/api"), "http://localhost:8080");
+        assert_eq!(
+            origin("https://gitlab.example.com"),
+            "https://gitlab.example.com"
+        );
+        assert_eq!(
+            origin("https://example.com?redirect=https://evil.example.com/"),
+            "https://example.com"
+        );
+    }
+
+    #[test]
+    fn test_is_valid_header() {
+        assert!(is_valid_header("Authorization", "Bearer abc.def"));
+        assert!(is_valid_header("X-JFrog-Art-Api", "key\twith tab"));
+        assert!(!is_valid_header("", "value"));
+        assert!(!is_valid_header("Bad Name", "value"));
+        assert!(!is_valid_header("X-Foo", "bar\r\nX-Bar: baz"));
+        assert!(!is_valid_header("X-Foo", "bar\0"));
+    }
+
+    #[test]
+    fn debug_redacts_header_values() {
+        let request = HttpRequest::new("https://example.com/tool.tar.gz")
+            .header("Authorization", "Bearer s3cr3t");
+        let debug = format!("{:?}", request);
+        assert!(debug.contains("Authorization"));
+        assert!(!debug.contains("s3cr3t"));
+    }
+}
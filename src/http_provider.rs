@@ -7,7 +7,7 @@
  * of this source tree.
  */
 
-use std::ffi::OsString;
+use std::collections::BTreeMap;
 use std::path::Path;
 
 use anyhow::Context as _;
@@ -15,10 +15,11 @@ use serde::Deserialize;
 use serde_jsonrc::value::Value;
 
 use crate::config::ArtifactEntry;
-use crate::curl::CurlCommand;
-use crate::curl::FetchContext;
+use crate::http_client::FetchContext;
+use crate::http_client::HttpRequest;
 use crate::provider::Provider;
 use crate::util::file_lock::FileLock;
+use crate::util::interpolate_env::interpolate_env;
 
 pub struct HttpProvider {}
 
@@ -29,27 +30,56 @@ impl Provider for HttpProvider {
         destination: &Path,
         _fetch_lock: &FileLock,
         artifact_entry: &ArtifactEntry,
+        _dotslash_file: &Path,
     ) -> anyhow::Result<()> {
         let config = HttpProviderConfig::deserialize(provider_config)?;
-        let url = config.url;
-        let url_os_str = OsString::from(url.clone());
-        let curl_cmd = CurlCommand::new(&url_os_str);
-        // Currently, we always disable the progress bar, but we plan to add a
-        // configuration option to enable it.
-        let show_progress = false;
-        let fetch_context = FetchContext {
-            artifact_name: url.as_str(),
-            content_length: artifact_entry.size,
-            show_progress,
-        };
-        curl_cmd
-            .get_request(destination, &fetch_context)
-            .with_context(|| format!("failed to fetch `{}`", url))?;
-        Ok(())
+        let mut headers = Vec::with_capacity(config.headers.len());
+        for (name, value) in config.headers {
+            let value = interpolate_env(&value)
+                .with_context(|| format!("failed to expand value of header `{}`", name))?;
+            headers.push((name, value));
+        }
+        fetch_url_with_headers(&config.url, headers, destination, artifact_entry)
     }
 }
 
+/// Fetches `url` with an HTTP GET request and writes the response body to
+/// `destination`.
+pub fn fetch_url(
+    url: &str,
+    destination: &Path,
+    artifact_entry: &ArtifactEntry,
+) -> anyhow::Result<()> {
+    fetch_url_with_headers(url, Vec::new(), destination, artifact_entry)
+}
+
+fn fetch_url_with_headers(
+    url: &str,
+    headers: Vec<(String, String)>,
+    destination: &Path,
+    artifact_entry: &ArtifactEntry,
+) -> anyhow::Result<()> {
+    let mut request = HttpRequest::new(url);
+    request.headers = headers;
+    // Currently, we always disable the progress bar, but we plan to add a
+    // configuration option to enable it.
+    let show_progress = false;
+    let fetch_context = FetchContext {
+        artifact_name: url,
+        content_length: artifact_entry.size,
+        show_progress,
+    };
+    request
+        .get_request(destination, &fetch_context)
+        .with_context(|| format!("failed to fetch `{}`", url))?;
+    Ok(())
+}
+
 #[derive(Deserialize, Debug, PartialEq)]
 struct HttpProviderConfig {
     url: String,
+    /// Extra request headers. Values may reference environment variables as
+    /// `${env:NAME}`, e.g. `"Authorization": "Bearer ${env:ARTIFACTORY_TOKEN}"`.
+    #[serde(default)]
+    headers: BTreeMap<String, String>,
 }
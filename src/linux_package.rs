@@ -0,0 +1,541 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Unpacking of the files in `.deb` and `.rpm` packages. Only the payload is
+//! extracted: maintainer scripts, triggers, and dependencies are ignored.
+
+use std::collections::HashMap;
+use std::io;
+use std::io::BufReader;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::decompress;
+use crate::decompress::EntryFilter;
+use crate::util::fs_ctx;
+
+/// Signature and header sections of an `.rpm` start with these bytes.
+const RPM_HEADER_MAGIC: [u8; 4] = [0x8e, 0xad, 0xe8, 0x01];
+const RPM_LEAD_SIZE: usize = 96;
+const RPMTAG_PAYLOADCOMPRESSOR: u32 = 1125;
+const RPM_STRING_TYPE: u32 = 6;
+
+const CPIO_HEADER_SIZE: usize = 110;
+const CPIO_TRAILER: &str = "TRAILER!!!";
+
+const S_IFMT: u32 = 0o170000;
+const S_IFDIR: u32 = 0o040000;
+const S_IFREG: u32 = 0o100000;
+const S_IFLNK: u32 = 0o120000;
+
+/// Extracts the `data.tar[.COMPRESSION]` member of a `.deb` into
+/// `destination_dir`.
+///
+/// https://manpages.debian.org/deb.5
+pub fn unpack_deb(deb_file: &Path, destination_dir: &Path, filter: &EntryFilter) -> io::Result<()> {
+    // See decompress::untar() for why the destination dir is canonicalized.
+    fs_ctx::create_dir_all(destination_dir)?;
+    let destination_dir = fs_ctx::canonicalize(destination_dir)?;
+    let file = fs_ctx::file_open(deb_file)?;
+    let mut archive = ar::Archive::new(BufReader::new(file));
+    while let Some(entry) = archive.next_entry() {
+        let entry = entry?;
+        let identifier = String::from_utf8_lossy(entry.header().identifier()).into_owned();
+        // GNU ar terminates names with a `/`.
+        let Some(extension) = identifier.trim_end_matches('/').strip_prefix("data.tar") else {
+            continue;
+        };
+        let compressor = match extension {
+            "" => None,
+            ".gz" => Some("gzip"),
+            ".bz2" => Some("bzip2"),
+            ".lzma" => Some("lzma"),
+            ".xz" => Some("xz"),
+            ".zst" => Some("zstd"),
+            _ => {
+                return Err(invalid_data(format!(
+                    "unsupported .deb payload `{}`",
+                    identifier
+                )));
+            }
+        };
+        let reader = decompressing_reader(compressor, entry)?;
+        return decompress::unpack(tar::Archive::new(reader), &destination_dir, filter);
+    }
+    Err(invalid_data(".deb has no `data.tar` member".to_owned()))
+}
+
+/// Extracts the cpio payload of an `.rpm` into `destination_dir`.
+///
+/// https://rpm-software-management.github.io/rpm/manual/format.html
+pub fn unpack_rpm(rpm_file: &Path, destination_dir: &Path, filter: &EntryFilter) -> io::Result<()> {
+    // See decompress::untar() for why the destination dir is canonicalized.
+    fs_ctx::create_dir_all(destination_dir)?;
+    let destination_dir = fs_ctx::canonicalize(destination_dir)?;
+    let file = fs_ctx::file_open(rpm_file)?;
+    let mut reader = BufReader::new(file);
+
+    let mut lead = [0; RPM_LEAD_SIZE];
+    reader.read_exact(&mut lead)?;
+    if lead[..4] != [0xed, 0xab, 0xee, 0xdb] {
+        return Err(invalid_data("not an .rpm".to_owned()));
+    }
+    // The signature header is padded so that the main header is aligned to
+    // 8 bytes.
+    let signature = RpmHeader::read(&mut reader)?;
+    let padding = (8 - signature.data.len() % 8) % 8;
+    io::copy(&mut (&mut reader).take(padding as u64), &mut io::sink())?;
+    let header = RpmHeader::read(&mut reader)?;
+
+    // Payloads were gzip-compressed before the tag was introduced.
+    let compressor = header.string(RPMTAG_PAYLOADCOMPRESSOR)?;
+    let compressor = match compressor.as_deref().unwrap_or("gzip") {
+        // An uncompressed payload.
+        "identity" => None,
+        compressor => Some(compressor),
+    };
+    let payload = decompressing_reader(compressor, reader)?;
+    unpack_cpio(payload, &destination_dir, filter)
+}
+
+/// A signature or header section of an `.rpm`.
+struct RpmHeader {
+    /// 16-byte entries of tag, type, offset into `data`, and count.
+    index: Vec<u8>,
+    data: Vec<u8>,
+}
+
+impl RpmHeader {
+    fn read(reader: &mut impl Read) -> io::Result<RpmHeader> {
+        let mut intro = [0; 16];
+        reader.read_exact(&mut intro)?;
+        if intro[..4] != RPM_HEADER_MAGIC {
+            return Err(invalid_data("invalid .rpm header".to_owned()));
+        }
+        let num_entries = be_u32(&intro[8..12]) as u64;
+        let data_size = be_u32(&intro[12..16]) as u64;
+        Ok(RpmHeader {
+            index: read_exactly(reader, num_entries * 16)?,
+            data: read_exactly(reader, data_size)?,
+        })
+    }
+
+    /// Returns the value of the string tag `tag`, if present.
+    fn string(&self, tag: u32) -> io::Result<Option<String>> {
+        for entry in self.index.chunks_exact(16) {
+            if be_u32(&entry[..4]) != tag {
+                continue;
+            }
+            if be_u32(&entry[4..8]) != RPM_STRING_TYPE {
+                return Err(invalid_data(format!(
+                    ".rpm header tag {} is not a string",
+                    tag
+                )));
+            }
+            let value = self
+                .data
+                .get(be_u32(&entry[8..12]) as usize..)
+                .and_then(|value| value.split(|&b| b == 0).next())
+                .ok_or_else(|| invalid_data("invalid .rpm header".to_owned()))?;
+            return Ok(Some(String::from_utf8_lossy(value).into_owned()));
+        }
+        Ok(None)
+    }
+}
+
+/// Reads `size` bytes without allocating them up front, as the size comes
+/// from the file.
+fn read_exactly(reader: &mut impl Read, size: u64) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    reader.take(size).read_to_end(&mut bytes)?;
+    if bytes.len() as u64 != size {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+/// Extracts a cpio archive in the "new ASCII" (`070701`) or "new CRC"
+/// (`070702`) format, which is what rpm uses.
+fn unpack_cpio(
+    mut reader: impl Read,
+    destination_dir: &Path,
+    filter: &EntryFilter,
+) -> io::Result<()> {
+    // As with decompress::unzip(), directory permissions are applied and
+    // symlinks are created after all other entries are written.
+    let mut dir_modes = Vec::new();
+    let mut symlinks = Vec::new();
+    // Hard links share an inode, and only the last of them has the data.
+    let mut pending_links = HashMap::<(u32, u32), (u32, Vec<PathBuf>)>::new();
+    let mut limits = decompress::ExtractionLimits::from_env()?;
+    let mut offset = 0usize;
+    loop {
+        let mut header = [0; CPIO_HEADER_SIZE];
+        reader.read_exact(&mut header)?;
+        if &header[..6] != b"070701" && &header[..6] != b"070702" {
+            return Err(invalid_data("unsupported cpio format".to_owned()));
+        }
+        // The header is the magic followed by 8-digit hex numbers.
+        let field = |i: usize| {
+            std::str::from_utf8(&header[6 + i * 8..14 + i * 8])
+                .ok()
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| invalid_data("invalid cpio header".to_owned()))
+        };
+        let inode = field(0)?;
+        let mode = field(1)?;
+        let num_links = field(4)?;
+        let file_size = field(6)? as usize;
+        let device = field(7)?;
+        let name_size = field(11)? as usize;
+        offset += CPIO_HEADER_SIZE;
+
+        let name = read_exactly(&mut reader, name_size as u64)?;
+        offset += name_size;
+        skip_padding(&mut reader, &mut offset)?;
+        let name = String::from_utf8_lossy(name.strip_suffix(&[0]).unwrap_or(&name)).into_owned();
+        if name == CPIO_TRAILER {
+            // An empty file has no data for any of its names, so its links
+            // are still pending.
+            for (mode, links) in pending_links.into_values() {
+                write_hard_linked_file(None, links, io::empty(), mode)?;
+            }
+            break;
+        }
+
+        let relative_path = decompress::enclosed_path(Path::new(&name)).ok_or_else(|| {
+            invalid_data(format!("cpio entry `{}` escapes the destination", name))
+        })?;
+        limits.add_entry(&name, file_size as u64)?;
+        let path = filter
+            .map(&relative_path)
+            .map(|relative_path| destination_dir.join(relative_path));
+        let mut data = (&mut reader).take(file_size as u64);
+        match (mode & S_IFMT, path) {
+            (S_IFDIR, Some(path)) => {
+                fs_ctx::create_dir_all(&path)?;
+                dir_modes.push((path, mode));
+            }
+            (S_IFREG, path) if num_links > 1 && file_size == 0 => {
+                pending_links
+                    .entry((device, inode))
+                    .or_insert_with(|| (mode, Vec::new()))
+                    .1
+                    .extend(path);
+            }
+            (S_IFREG, path) => {
+                let (_, links) = pending_links.remove(&(device, inode)).unwrap_or_default();
+                write_hard_linked_file(path, links, &mut data, mode)?;
+            }
+            (S_IFLNK, Some(path)) => {
+                let mut target = String::new();
+                data.read_to_string(&mut target)?;
+                symlinks.push((name, path, target));
+            }
+            (S_IFDIR | S_IFLNK, None) => {}
+            _ => {
+                return Err(invalid_data(format!(
+                    "cpio entry `{}` is not a file, directory, or symlink",
+                    name
+                )));
+            }
+        }
+        // Consume whatever was not read, e.g., for a hard link without data.
+        io::copy(&mut data, &mut io::sink())?;
+        offset += file_size;
+        skip_padding(&mut reader, &mut offset)?;
+    }
+
+    for (name, path, target) in symlinks {
+        decompress::create_symlink(destination_dir, &name, &path, &target)?;
+    }
+
+    #[cfg(unix)]
+    for (path, mode) in dir_modes.into_iter().rev() {
+        decompress::set_unix_permissions(&path, mode)?;
+    }
+
+    Ok(())
+}
+
+/// Writes `data` to `path` and hard links `links` to it. If the entry with the
+/// data is not extracted but other names for the same inode are, the data goes
+/// to the first of them.
+#[cfg_attr(not(unix), allow(unused_variables))]
+fn write_hard_linked_file(
+    path: Option<PathBuf>,
+    mut links: Vec<PathBuf>,
+    mut data: impl Read,
+    mode: u32,
+) -> io::Result<()> {
+    let Some(path) = path.or_else(|| (!links.is_empty()).then(|| links.remove(0))) else {
+        return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+        fs_ctx::create_dir_all(parent)?;
+    }
+    let mut output = fs_ctx::file_create(&path)?;
+    io::copy(&mut data, &mut output)?;
+    drop(output);
+    #[cfg(unix)]
+    decompress::set_unix_permissions(&path, mode)?;
+    for link in links {
+        if let Some(parent) = link.parent() {
+            fs_ctx::create_dir_all(parent)?;
+        }
+        std::fs::hard_link(&path, &link)?;
+    }
+    Ok(())
+}
+
+/// Entries in a cpio archive are aligned to 4 bytes.
+fn skip_padding(reader: &mut impl Read, offset: &mut usize) -> io::Result<()> {
+    let padding = (4 - *offset % 4) % 4;
+    reader.read_exact(&mut [0; 3][..padding])?;
+    *offset += padding;
+    Ok(())
+}
+
+fn decompressing_reader<'a>(
+    compressor: Option<&str>,
+    reader: impl Read + 'a,
+) -> io::Result<Box<dyn Read + 'a>> {
+    Ok(match compressor {
+        None => Box::new(reader),
+        Some("gzip") => Box::new(flate2::read::MultiGzDecoder::new(reader)),
+        Some("bzip2") => Box::new(bzip2::read::MultiBzDecoder::new(reader)),
+        Some("xz") => Box::new(xz2::read::XzDecoder::new_multi_decoder(reader)),
+        Some("lzma") => Box::new(xz2::read::XzDecoder::new_stream(
+            reader,
+            xz2::stream::Stream::new_lzma_decoder(u64::MAX)?,
+        )),
+        Some("zstd") => Box::new(zstd::stream::read::Decoder::new(reader)?),
+        Some(compressor) => {
+            return Err(invalid_data(format!(
+                "unsupported payload compression `{}`",
+                compressor
+            )));
+        }
+    })
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes(bytes[..4].try_into().unwrap())
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use super::*;
+
+    fn cpio_entry(cpio: &mut Vec<u8>, name: &str, inode: u32, mode: u32, nlink: u32, data: &[u8]) {
+        let name = format!("{}\0", name);
+        write!(
+            cpio,
+            "070701{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}{:08x}",
+            inode,
+            mode,
+            0,
+            0,
+            nlink,
+            0,
+            data.len(),
+            0,
+            0,
+            0,
+            0,
+            name.len(),
+            0,
+        )
+        .unwrap();
+        cpio.extend_from_slice(name.as_bytes());
+        cpio.resize(cpio.len().next_multiple_of(4), 0);
+        cpio.extend_from_slice(data);
+        cpio.resize(cpio.len().next_multiple_of(4), 0);
+    }
+
+    fn cpio_archive() -> Vec<u8> {
+        let mut cpio = Vec::new();
+        cpio_entry(&mut cpio, "./usr", 1, S_IFDIR | 0o755, 2, b"");
+        cpio_entry(
+            &mut cpio,
+            "./usr/bin/tool",
+            2,
+            S_IFREG | 0o4755,
+            1,
+            b"#!/bin/sh\n",
+        );
+        cpio_entry(&mut cpio, "./usr/bin/link", 3, S_IFLNK | 0o777, 1, b"tool");
+        cpio_entry(&mut cpio, "./usr/bin/a", 4, S_IFREG | 0o644, 2, b"");
+        cpio_entry(&mut cpio, "./usr/bin/b", 4, S_IFREG | 0o644, 2, b"same");
+        cpio_entry(&mut cpio, CPIO_TRAILER, 0, 0, 1, b"");
+        cpio
+    }
+
+    fn rpm_header(entries: &[(u32, u32, &[u8])]) -> Vec<u8> {
+        let mut index = Vec::new();
+        let mut data = Vec::new();
+        for (tag, kind, value) in entries {
+            for n in [*tag, *kind, data.len() as u32, 1] {
+                index.extend_from_slice(&n.to_be_bytes());
+            }
+            data.extend_from_slice(value);
+        }
+        let mut header = RPM_HEADER_MAGIC.to_vec();
+        header.extend_from_slice(&[0; 4]);
+        header.extend_from_slice(&(entries.len() as u32).to_be_bytes());
+        header.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        header.extend(index);
+        header.extend(data);
+        header
+    }
+
+    fn assert_unpacked(destination: &Path) {
+        let bin = destination.join("usr/bin");
+        assert_eq!(
+            std::fs::read_to_string(bin.join("tool")).unwrap(),
+            "#!/bin/sh\n"
+        );
+        assert_eq!(std::fs::read_to_string(bin.join("a")).unwrap(), "same");
+        assert_eq!(std::fs::read_to_string(bin.join("b")).unwrap(), "same");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt as _;
+            assert_eq!(
+                std::fs::read_link(bin.join("link")).unwrap(),
+                Path::new("tool")
+            );
+            let mode = fs_ctx::metadata(bin.join("tool"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o7777, 0o755);
+        }
+    }
+
+    #[test]
+    fn unpack_rpm_with_xz_payload() {
+        let mut payload = xz2::write::XzEncoder::new(Vec::new(), 6);
+        payload.write_all(&cpio_archive()).unwrap();
+        let payload = payload.finish().unwrap();
+
+        let mut rpm = vec![0xed, 0xab, 0xee, 0xdb];
+        rpm.resize(RPM_LEAD_SIZE, 0);
+        // A signature header whose size is not a multiple of 8.
+        let signature = rpm_header(&[(1000, 4, &[0, 0, 0, 1, 0])]);
+        rpm.extend_from_slice(&signature);
+        rpm.resize(rpm.len().next_multiple_of(8), 0);
+        rpm.extend(rpm_header(&[(
+            RPMTAG_PAYLOADCOMPRESSOR,
+            RPM_STRING_TYPE,
+            b"xz\0",
+        )]));
+        rpm.extend(payload);
+
+        let rpm_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(rpm_file.path(), rpm).unwrap();
+        let destination = tempfile::tempdir().unwrap();
+        unpack_rpm(rpm_file.path(), destination.path(), &EntryFilter::default()).unwrap();
+        assert_unpacked(destination.path());
+    }
+
+    #[test]
+    fn unpack_cpio_with_filter() {
+        let destination = tempfile::tempdir().unwrap();
+        // `a` and `b` are hard links, and only `b` has the data.
+        let filter = EntryFilter::new(&["bin/a".to_owned(), "bin/tool".to_owned()], 1).unwrap();
+        unpack_cpio(cpio_archive().as_slice(), destination.path(), &filter).unwrap();
+        let bin = destination.path().join("bin");
+        assert_eq!(
+            std::fs::read_to_string(bin.join("tool")).unwrap(),
+            "#!/bin/sh\n"
+        );
+        assert_eq!(std::fs::read_to_string(bin.join("a")).unwrap(), "same");
+        assert!(!bin.join("b").exists());
+        assert!(!bin.join("link").exists());
+        assert!(!destination.path().join("usr").exists());
+    }
+
+    #[test]
+    fn unpack_cpio_empty_hard_linked_file() {
+        let mut cpio = Vec::new();
+        cpio_entry(&mut cpio, "./etc/a", 1, S_IFREG | 0o600, 2, b"");
+        cpio_entry(&mut cpio, "./etc/b", 1, S_IFREG | 0o600, 2, b"");
+        cpio_entry(&mut cpio, CPIO_TRAILER, 0, 0, 1, b"");
+        let destination = tempfile::tempdir().unwrap();
+        unpack_cpio(cpio.as_slice(), destination.path(), &EntryFilter::default()).unwrap();
+        let etc = destination.path().join("etc");
+        assert_eq!(std::fs::read_to_string(etc.join("a")).unwrap(), "");
+        assert_eq!(std::fs::read_to_string(etc.join("b")).unwrap(), "");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt as _;
+            let a = fs_ctx::metadata(etc.join("a")).unwrap();
+            let b = fs_ctx::metadata(etc.join("b")).unwrap();
+            assert_eq!(a.ino(), b.ino());
+            assert_eq!(a.mode() & 0o7777, 0o600);
+        }
+    }
+
+    #[test]
+    fn unpack_cpio_rejects_path_traversal() {
+        let mut cpio = Vec::new();
+        cpio_entry(&mut cpio, "../evil", 1, S_IFREG | 0o644, 1, b"");
+        cpio_entry(&mut cpio, CPIO_TRAILER, 0, 0, 1, b"");
+        let destination = tempfile::tempdir().unwrap();
+        let err =
+            unpack_cpio(cpio.as_slice(), destination.path(), &EntryFilter::default()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "cpio entry `../evil` escapes the destination"
+        );
+    }
+
+    #[test]
+    fn unpack_deb_data_member() {
+        let mut data_tar = tar::Builder::new(Vec::new());
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_mode(0o755);
+        data_tar
+            .append_data(&mut header, "./usr/bin/tool", &b"tool"[..])
+            .unwrap();
+        let mut data_tar_gz =
+            flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        data_tar_gz
+            .write_all(&data_tar.into_inner().unwrap())
+            .unwrap();
+        let data_tar_gz = data_tar_gz.finish().unwrap();
+
+        let deb_file = tempfile::NamedTempFile::new().unwrap();
+        let mut deb = ar::Builder::new(deb_file.reopen().unwrap());
+        for (name, data) in [
+            ("debian-binary", &b"2.0\n"[..]),
+            ("control.tar.gz", &b"not read"[..]),
+            ("data.tar.gz", &data_tar_gz[..]),
+        ] {
+            deb.append(&ar::Header::new(name.into(), data.len() as u64), data)
+                .unwrap();
+        }
+        drop(deb);
+
+        let destination = tempfile::tempdir().unwrap();
+        unpack_deb(deb_file.path(), destination.path(), &EntryFilter::default()).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(destination.path().join("usr/bin/tool")).unwrap(),
+            "tool",
+        );
+    }
+}
@@ -9,7 +9,9 @@
 
 mod artifact_location;
 mod artifact_path;
+mod cas_provider;
 mod config;
+mod credentials;
 mod curl;
 mod decompress;
 mod digest;
@@ -17,13 +19,23 @@ mod dotslash_cache;
 mod download;
 mod execution;
 mod fetch_method;
+mod file_provider;
 mod github_release_provider;
+mod gitlab_release_provider;
+mod http_client;
 mod http_provider;
+mod linux_package;
+#[cfg(feature = "native-http")]
+mod native_http;
+mod oci_provider;
 mod platform;
+mod plugin_provider;
 mod print_entry_for_url;
 mod progress;
 mod provider;
+mod s3_provider;
 mod subcommand;
+mod url_rewrite;
 mod util;
 
 use std::env;
@@ -31,10 +43,16 @@ use std::process::ExitCode;
 
 use anyhow::format_err;
 
+use crate::cas_provider::CasProvider;
+use crate::file_provider::FileProvider;
 use crate::github_release_provider::GitHubReleaseProvider;
+use crate::gitlab_release_provider::GitLabReleaseProvider;
 use crate::http_provider::HttpProvider;
+use crate::oci_provider::OciProvider;
+use crate::plugin_provider::PluginProvider;
 use crate::provider::Provider;
 use crate::provider::ProviderFactory;
+use crate::s3_provider::S3Provider;
 
 fn main() -> ExitCode {
     let args = env::args_os();
@@ -47,9 +65,17 @@ struct DefaultProviderFactory;
 impl ProviderFactory for DefaultProviderFactory {
     fn get_provider(&self, provider_type: &str) -> anyhow::Result<Box<dyn Provider>> {
         match provider_type {
+            "cas" => Ok(Box::new(CasProvider {})),
+            "file" => Ok(Box::new(FileProvider {})),
             "http" => Ok(Box::new(HttpProvider {})),
+            "oci" => Ok(Box::new(OciProvider {})),
             "github-release" => Ok(Box::new(GitHubReleaseProvider {})),
-            _ => Err(format_err!("unknown provider type: `{}`", provider_type)),
+            "gitlab-release" => Ok(Box::new(GitLabReleaseProvider {})),
+            "s3" => Ok(Box::new(S3Provider {})),
+            _ => match PluginProvider::find(provider_type) {
+                Some(plugin) => Ok(Box::new(plugin)),
+                None => Err(format_err!("unknown provider type: `{}`", provider_type)),
+            },
         }
     }
 }
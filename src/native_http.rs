@@ -0,0 +1,225 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! An `HttpBackend` that makes requests in-process, for environments where
+//! `curl` is not available.
+
+use std::fs::File;
+use std::io;
+use std::io::Read as _;
+use std::io::Write as _;
+use std::path::Path;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use thiserror::Error;
+use ureq::Agent;
+use ureq::AgentBuilder;
+use ureq::ErrorKind;
+use ureq::OrAnyStatus as _;
+use ureq::RedirectAuthHeaders;
+use ureq::Response;
+use url::Url;
+
+use crate::http_client::origin;
+use crate::http_client::HttpBackend;
+use crate::http_client::HttpError;
+use crate::http_client::HttpRequest;
+use crate::http_client::MAX_REDIRECTS;
+use crate::http_client::NUM_TRANSIENT_ERROR_MAX_ATTEMPTS;
+use crate::http_client::USER_AGENT;
+use crate::util::http_status::HttpStatus;
+
+/// Status codes that curl's `--retry` considers transient.
+const TRANSIENT_STATUS_CODES: &[u16] = &[408, 429, 500, 502, 503, 504];
+
+pub struct NativeBackend;
+
+#[derive(Debug, Error)]
+pub enum NativeHttpError {
+    // The server returned 4xx or 5xx.
+    #[error("GET {0}")]
+    HttpStatus(String, #[source] HttpStatus),
+
+    // Resolving, connecting, or sending the request failed.
+    #[error("GET {0}")]
+    Transport(String, #[source] Box<ureq::Transport>),
+
+    // The connection failed while the response body was being received.
+    #[error("GET {0}")]
+    ReadBody(String, #[source] io::Error),
+
+    // A redirect that `send()` followed itself had an invalid `Location`.
+    #[error("GET {0}: invalid redirect to `{1}`")]
+    InvalidRedirect(String, String),
+
+    // There were more than `MAX_REDIRECTS` redirects that `send()` followed
+    // itself.
+    #[error("GET {0}: too many redirects")]
+    TooManyRedirects(String),
+
+    #[error("failed to write `{0}`")]
+    WriteBody(PathBuf, #[source] io::Error),
+}
+
+impl NativeHttpError {
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Transport(_, transport) => {
+                matches!(
+                    transport.kind(),
+                    ErrorKind::ConnectionFailed | ErrorKind::Io
+                )
+            }
+            Self::ReadBody(..) => true,
+            _ => false,
+        }
+    }
+
+    pub fn is_too_many_requests(&self) -> bool {
+        matches!(self, Self::HttpStatus(_, HttpStatus::TooManyRequests))
+    }
+}
+
+impl HttpBackend for NativeBackend {
+    fn get_to_file(&self, request: &HttpRequest, target: &Path) -> Result<(), HttpError> {
+        let response = call(request)?;
+        let mut reader = response.into_reader();
+        let mut file =
+            File::create(target).map_err(|e| NativeHttpError::WriteBody(target.to_owned(), e))?;
+        let mut buf = vec![0; 64 * 1024];
+        loop {
+            let n = reader
+                .read(&mut buf)
+                .map_err(|e| NativeHttpError::ReadBody(request.url.to_owned(), e))?;
+            if n == 0 {
+                break;
+            }
+            file.write_all(&buf[..n])
+                .map_err(|e| NativeHttpError::WriteBody(target.to_owned(), e))?;
+        }
+        Ok(())
+    }
+
+    fn get_body(&self, request: &HttpRequest) -> Result<Vec<u8>, HttpError> {
+        let response = call(request)?;
+        let mut body = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut body)
+            .map_err(|e| NativeHttpError::ReadBody(request.url.to_owned(), e))?;
+        Ok(body)
+    }
+
+    fn get_headers(
+        &self,
+        request: &HttpRequest,
+    ) -> Result<(u16, Vec<(String, String)>), HttpError> {
+        let response = send(request)?;
+        let headers = response
+            .headers_names()
+            .into_iter()
+            .flat_map(|name| {
+                response
+                    .all(&name)
+                    .into_iter()
+                    .map(|value| (name.clone(), value.to_owned()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        Ok((response.status(), headers))
+    }
+}
+
+fn agent(redirects: u32) -> Agent {
+    AgentBuilder::new()
+        .user_agent(USER_AGENT)
+        .redirects(redirects)
+        // Like curl, do not send credentials to a different host after a
+        // redirect (e.g., from a release page to a storage bucket).
+        .redirect_auth_headers(RedirectAuthHeaders::SameHost)
+        .build()
+}
+
+fn build(agent: &Agent, url: &str, headers: &[(String, String)]) -> ureq::Request {
+    let mut ureq_request = agent.get(url);
+    for (name, value) in headers {
+        ureq_request = ureq_request.set(name, value);
+    }
+    ureq_request
+}
+
+/// Sends the request and returns the final response, whatever its status.
+///
+/// ureq sends the headers on every redirect, and it only drops
+/// `Authorization` when the host changes. So if there are headers, redirects
+/// are followed here instead until one leaves the origin of the request,
+/// after which ureq follows the rest without the headers.
+fn send(request: &HttpRequest) -> Result<Response, NativeHttpError> {
+    let get = |agent: &Agent, url: &str, headers: &[(String, String)]| {
+        build(agent, url, headers)
+            .call()
+            .or_any_status()
+            .map_err(|transport| transport_error(request, transport))
+    };
+    if request.headers.is_empty() {
+        return get(&agent(MAX_REDIRECTS), request.url, &[]);
+    }
+
+    let no_redirects = agent(0);
+    let mut url = request.url.to_owned();
+    for _ in 0..MAX_REDIRECTS {
+        let response = get(&no_redirects, &url, &request.headers)?;
+        let location = match (response.status(), response.header("location")) {
+            (301 | 302 | 303 | 307 | 308, Some(location)) => location,
+            _ => return Ok(response),
+        };
+        let location = Url::parse(&url)
+            .and_then(|url| url.join(location))
+            .map_err(|_| {
+                NativeHttpError::InvalidRedirect(request.url.to_owned(), location.to_owned())
+            })?
+            .to_string();
+        if origin(&location) != origin(request.url) {
+            return get(&agent(MAX_REDIRECTS), &location, &[]);
+        }
+        url = location;
+    }
+    Err(NativeHttpError::TooManyRedirects(request.url.to_owned()))
+}
+
+/// Sends the request, retrying transient errors with the same backoff as
+/// curl's `--retry`: 1s, 2s, 4s, ...
+fn call(request: &HttpRequest) -> Result<Response, NativeHttpError> {
+    let mut delay = Duration::from_secs(1);
+    let mut attempts = 0;
+    loop {
+        let response = send(request)?;
+        let code = response.status();
+        if code < 400 {
+            return Ok(response);
+        } else if TRANSIENT_STATUS_CODES.contains(&code)
+            && attempts < NUM_TRANSIENT_ERROR_MAX_ATTEMPTS
+        {
+            attempts += 1;
+            thread::sleep(delay);
+            delay *= 2;
+        } else {
+            return Err(NativeHttpError::HttpStatus(
+                request.url.to_owned(),
+                HttpStatus::from(code as usize),
+            ));
+        }
+    }
+}
+
+fn transport_error(request: &HttpRequest, transport: ureq::Transport) -> NativeHttpError {
+    NativeHttpError::Transport(request.url.to_owned(), Box::new(transport))
+}
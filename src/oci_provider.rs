@@ -0,0 +1,527 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::format_err;
+use anyhow::Context as _;
+use serde::Deserialize;
+use serde_jsonrc::value::Value;
+
+use crate::config::ArtifactEntry;
+use crate::http_client::FetchContext;
+use crate::http_client::HttpRequest;
+use crate::provider::Provider;
+use crate::util::file_lock::FileLock;
+use crate::util::percent_encode::percent_encode;
+
+/// Annotation that ORAS uses to record the filename of a pushed file.
+const TITLE_ANNOTATION: &str = "org.opencontainers.image.title";
+
+const MANIFEST_MEDIA_TYPES: &str = "application/vnd.oci.image.manifest.v1+json, \
+     application/vnd.docker.distribution.manifest.v2+json";
+
+/// Fetches a blob from a registry that implements the OCI distribution API,
+/// such as one that artifacts were pushed to with ORAS.
+///
+/// https://github.com/opencontainers/distribution-spec/blob/main/spec.md
+pub struct OciProvider {}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct OciProviderConfig {
+    /// Host (and optional port) of the registry, such as `ghcr.io`. HTTPS is
+    /// used unless the value starts with `http://`.
+    registry: String,
+    repository: String,
+    /// Digest of the blob, such as `sha256:abc...`.
+    digest: Option<String>,
+    /// Tag of the manifest that lists the blob as a layer. The layer is
+    /// selected with `layer` or `annotation`, or is the only layer.
+    tag: Option<String>,
+    layer: Option<usize>,
+    annotation: Option<HashMap<String, String>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Manifest {
+    #[serde(default)]
+    layers: Vec<Descriptor>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Descriptor {
+    digest: String,
+    #[serde(default)]
+    annotations: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct TokenResponse {
+    token: Option<String>,
+    access_token: Option<String>,
+}
+
+impl Provider for OciProvider {
+    fn fetch_artifact(
+        &self,
+        provider_config: &Value,
+        destination: &Path,
+        _fetch_lock: &FileLock,
+        artifact_entry: &ArtifactEntry,
+        _dotslash_file: &Path,
+    ) -> anyhow::Result<()> {
+        let config = OciProviderConfig::deserialize(provider_config)?;
+        // Everything that goes into a URL is validated against the grammar
+        // in the distribution spec, which only allows characters that do not
+        // need to be percent-encoded.
+        let base_url = registry_base_url(&config.registry)?;
+        validate_repository(&config.repository)?;
+        if let Some(tag) = &config.tag {
+            validate_tag(tag)?;
+        }
+        if let Some(digest) = &config.digest {
+            validate_digest(digest)?;
+        }
+        let repository_url = format!("{}/v2/{}", base_url, config.repository);
+        let token = get_bearer_token(&base_url, &config.repository)?;
+
+        let digest = match (&config.digest, &config.tag) {
+            (Some(digest), None) => digest.clone(),
+            (None, Some(tag)) => {
+                let manifest_url = format!("{}/manifests/{}", repository_url, tag);
+                let request = with_token(
+                    HttpRequest::new(&manifest_url).header("Accept", MANIFEST_MEDIA_TYPES),
+                    token.as_deref(),
+                );
+                let body = request
+                    .get_body()
+                    .with_context(|| format!("failed to fetch manifest for tag `{}`", tag))?;
+                let manifest = serde_jsonrc::from_slice::<Manifest>(&body)
+                    .with_context(|| format!("failed to parse manifest for tag `{}`", tag))?;
+                let digest =
+                    &select_layer(&manifest, config.layer, config.annotation.as_ref())?.digest;
+                validate_digest(digest)
+                    .with_context(|| format!("bad layer in manifest for tag `{}`", tag))?;
+                digest.clone()
+            }
+            _ => {
+                return Err(format_err!(
+                    "exactly one of `digest` or `tag` must be specified"
+                ));
+            }
+        };
+
+        let blob_url = format!("{}/blobs/{}", repository_url, digest);
+        let request = with_token(HttpRequest::new(&blob_url), token.as_deref());
+        let fetch_context = FetchContext {
+            artifact_name: blob_url.as_str(),
+            content_length: artifact_entry.size,
+            show_progress: false,
+        };
+        request
+            .get_request(destination, &fetch_context)
+            .with_context(|| format!("failed to fetch `{}`", blob_url))?;
+        Ok(())
+    }
+}
+
+/// Returns `scheme://host[:port]` for `registry`, which may start with
+/// `http://` or `https://`.
+fn registry_base_url(registry: &str) -> anyhow::Result<String> {
+    let (scheme, host) = match registry.split_once("://") {
+        Some((scheme @ ("http" | "https"), host)) => (scheme, host),
+        Some((scheme, _)) => {
+            return Err(format_err!(
+                "unsupported scheme `{}` in registry `{}`: must be `http` or `https`",
+                scheme,
+                registry,
+            ));
+        }
+        None => ("https", registry),
+    };
+    let host = host.trim_end_matches('/');
+    if host.is_empty() || host.contains(['/', '?', '#', '@']) {
+        return Err(format_err!(
+            "invalid registry `{}`: must be a host with an optional port",
+            registry,
+        ));
+    }
+    Ok(format!("{}://{}", scheme, host))
+}
+
+fn is_lower_alphanumeric(b: u8) -> bool {
+    b.is_ascii_lowercase() || b.is_ascii_digit()
+}
+
+/// `[a-z0-9]+((\.|_|__|-+)[a-z0-9]+)*(\/[a-z0-9]+((\.|_|__|-+)[a-z0-9]+)*)*`
+fn validate_repository(repository: &str) -> anyhow::Result<()> {
+    let is_valid_component = |component: &str| {
+        component.bytes().next().is_some_and(is_lower_alphanumeric)
+            && component.bytes().last().is_some_and(is_lower_alphanumeric)
+            && component
+                .split(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+                .all(|separator| {
+                    matches!(separator, "" | "." | "_" | "__")
+                        || separator.bytes().all(|b| b == b'-')
+                })
+    };
+    if repository.split('/').all(is_valid_component) {
+        Ok(())
+    } else {
+        Err(format_err!(
+            "invalid repository `{}`: each `/`-separated component must be \
+             lowercase letters and digits separated by `.`, `_`, `__`, or `-`",
+            repository,
+        ))
+    }
+}
+
+/// `[a-zA-Z0-9_][a-zA-Z0-9._-]{0,127}`
+fn validate_tag(tag: &str) -> anyhow::Result<()> {
+    let is_valid = tag.len() <= 128
+        && tag
+            .bytes()
+            .next()
+            .is_some_and(|b| b.is_ascii_alphanumeric() || b == b'_')
+        && tag
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b'-'));
+    if is_valid {
+        Ok(())
+    } else {
+        Err(format_err!(
+            "invalid tag `{}`: must be at most 128 letters, digits, `_`, `.`, or `-`, \
+             and must not start with `.` or `-`",
+            tag,
+        ))
+    }
+}
+
+/// `ALGORITHM:HEX`, where `ALGORITHM` is `[a-z0-9]+([+._-][a-z0-9]+)*`.
+fn validate_digest(digest: &str) -> anyhow::Result<()> {
+    let is_valid = digest.split_once(':').is_some_and(|(algorithm, hex)| {
+        algorithm
+            .split(['+', '.', '_', '-'])
+            .all(|part| !part.is_empty() && part.bytes().all(is_lower_alphanumeric))
+            && !hex.is_empty()
+            && hex.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+    });
+    if is_valid {
+        Ok(())
+    } else {
+        Err(format_err!(
+            "invalid digest `{}`: must be `ALGORITHM:HEX`, such as `sha256:...`",
+            digest,
+        ))
+    }
+}
+
+fn with_token<'a>(request: HttpRequest<'a>, token: Option<&str>) -> HttpRequest<'a> {
+    match token {
+        Some(token) => request.header("Authorization", format!("Bearer {}", token)),
+        None => request,
+    }
+}
+
+/// Asks the registry whether authentication is required. If the registry
+/// responds with a bearer challenge, a pull token is requested from the token
+/// service that the challenge points to. `HttpRequest` adds the user's
+/// credentials for the token service, if any; otherwise the token is
+/// anonymous.
+fn get_bearer_token(base_url: &str, repository: &str) -> anyhow::Result<Option<String>> {
+    let ping_url = format!("{}/v2/", base_url);
+    let (status, headers) = HttpRequest::new(&ping_url)
+        .get_headers()
+        .with_context(|| format!("failed to reach registry `{}`", base_url))?;
+    if status != 401 {
+        return Ok(None);
+    }
+
+    let challenge = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("www-authenticate"))
+        .and_then(|(_, value)| parse_bearer_challenge(value));
+    let Some(params) = challenge else {
+        // Nothing we can do anonymously: let the actual request fail.
+        return Ok(None);
+    };
+    let realm = params
+        .get("realm")
+        .with_context(|| format!("bearer challenge from `{}` has no realm", base_url))?;
+
+    let scope = format!("repository:{}:pull", repository);
+    let mut token_url = format!("{}?scope={}", realm, percent_encode(&scope, true));
+    if let Some(service) = params.get("service") {
+        token_url.push_str("&service=");
+        token_url.push_str(&percent_encode(service, true));
+    }
+    let body = HttpRequest::new(&token_url)
+        .get_body()
+        .with_context(|| format!("failed to get token from `{}`", realm))?;
+    let response = serde_jsonrc::from_slice::<TokenResponse>(&body)
+        .with_context(|| format!("failed to parse token from `{}`", realm))?;
+    response
+        .token
+        .or(response.access_token)
+        .map(Some)
+        .with_context(|| format!("no token in response from `{}`", realm))
+}
+
+/// Parses `Bearer realm="...",service="...",scope="..."`.
+fn parse_bearer_challenge(header: &str) -> Option<HashMap<String, String>> {
+    let (scheme, mut rest) = header.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+
+    let mut params = HashMap::new();
+    loop {
+        rest = rest.trim_start_matches([' ', ',']);
+        let Some((key, value)) = rest.split_once('=') else {
+            break;
+        };
+        let (value, remainder) = match value.strip_prefix('"') {
+            Some(quoted) => {
+                let (value, remainder) = quoted.split_once('"')?;
+                (value, remainder)
+            }
+            None => value.split_once(',').unwrap_or((value, "")),
+        };
+        params.insert(key.trim().to_ascii_lowercase(), value.to_owned());
+        rest = remainder;
+    }
+    Some(params)
+}
+
+fn select_layer<'a>(
+    manifest: &'a Manifest,
+    layer: Option<usize>,
+    annotation: Option<&HashMap<String, String>>,
+) -> anyhow::Result<&'a Descriptor> {
+    match (layer, annotation) {
+        (Some(index), None) => manifest.layers.get(index).with_context(|| {
+            format!(
+                "layer {} requested, but manifest has {} layers",
+                index,
+                manifest.layers.len(),
+            )
+        }),
+        (None, Some(annotation)) => {
+            let mut matches = manifest.layers.iter().filter(|layer| {
+                annotation
+                    .iter()
+                    .all(|(key, value)| layer.annotations.get(key) == Some(value))
+            });
+            match (matches.next(), matches.next()) {
+                (Some(layer), None) => Ok(layer),
+                (None, _) => Err(format_err!("no layer matches annotation {:?}", annotation)),
+                (Some(_), Some(_)) => Err(format_err!(
+                    "more than one layer matches annotation {:?}",
+                    annotation,
+                )),
+            }
+        }
+        (None, None) => match manifest.layers.as_slice() {
+            [layer] => Ok(layer),
+            layers => Err(format_err!(
+                "manifest has {} layers, so `layer` or `annotation` must be specified \
+                 (e.g., `{{\"{}\": \"NAME\"}}`)",
+                layers.len(),
+                TITLE_ANNOTATION,
+            )),
+        },
+        (Some(_), Some(_)) => Err(format_err!(
+            "only one of `layer` or `annotation` may be specified"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest() -> Manifest {
+        serde_jsonrc::from_str(
+            r#"{
+                "schemaVersion": 2,
+                "mediaType": "application/vnd.oci.image.manifest.v1+json",
+                "config": {
+                    "mediaType": "application/vnd.oci.empty.v1+json",
+                    "digest": "sha256:44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a",
+                    "size": 2
+                },
+                "layers": [
+                    {
+                        "mediaType": "application/vnd.oci.image.layer.v1.tar",
+                        "digest": "sha256:1111111111111111111111111111111111111111111111111111111111111111",
+                        "size": 123,
+                        "annotations": {
+                            "org.opencontainers.image.title": "tool-linux-x86_64.tar.gz"
+                        }
+                    },
+                    {
+                        "mediaType": "application/vnd.oci.image.layer.v1.tar",
+                        "digest": "sha256:2222222222222222222222222222222222222222222222222222222222222222",
+                        "size": 456,
+                        "annotations": {
+                            "org.opencontainers.image.title": "tool-macos.tar.gz"
+                        }
+                    }
+                ]
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_bearer_challenge() {
+        assert_eq!(
+            parse_bearer_challenge(
+                r#"Bearer realm="https://ghcr.io/token",service="ghcr.io",scope="repository:user/image:pull""#
+            ),
+            Some(HashMap::from([
+                ("realm".to_owned(), "https://ghcr.io/token".to_owned()),
+                ("service".to_owned(), "ghcr.io".to_owned()),
+                ("scope".to_owned(), "repository:user/image:pull".to_owned()),
+            ])),
+        );
+        assert_eq!(
+            parse_bearer_challenge(
+                r#"bearer realm="https://auth.docker.io/token", service=registry.docker.io"#
+            ),
+            Some(HashMap::from([
+                (
+                    "realm".to_owned(),
+                    "https://auth.docker.io/token".to_owned()
+                ),
+                ("service".to_owned(), "registry.docker.io".to_owned()),
+            ])),
+        );
+        assert_eq!(parse_bearer_challenge(r#"Basic realm="Registry""#), None);
+    }
+
+    #[test]
+    fn test_registry_base_url() {
+        assert_eq!(registry_base_url("ghcr.io").unwrap(), "https://ghcr.io");
+        assert_eq!(
+            registry_base_url("https://ghcr.io/").unwrap(),
+            "https://ghcr.io",
+        );
+        assert_eq!(
+            registry_base_url("http://localhost:5000").unwrap(),
+            "http://localhost:5000",
+        );
+        assert_eq!(
+            registry_base_url("ftp://ghcr.io").unwrap_err().to_string(),
+            "unsupported scheme `ftp` in registry `ftp://ghcr.io`: must be `http` or `https`",
+        );
+        assert!(registry_base_url("ghcr.io/v2/org").is_err());
+        assert!(registry_base_url("user@ghcr.io").is_err());
+        assert!(registry_base_url("https://").is_err());
+    }
+
+    #[test]
+    fn test_validate_repository() {
+        for repository in ["tool", "org/tool", "a.b/c_d/e__f/g--h", "0/1"] {
+            assert!(validate_repository(repository).is_ok(), "{repository}");
+        }
+        for repository in [
+            "",
+            "Org/tool",
+            "org//tool",
+            "org/tool/",
+            "../tool",
+            "org/-tool",
+            "a___b",
+            "a._b",
+            "org/tool?x",
+            "org/tool%2F",
+        ] {
+            assert!(validate_repository(repository).is_err(), "{repository}");
+        }
+    }
+
+    #[test]
+    fn test_validate_tag() {
+        for tag in ["latest", "v1.0.0", "_x", "1.0-rc_1", &"a".repeat(128)] {
+            assert!(validate_tag(tag).is_ok(), "{tag}");
+        }
+        for tag in ["", ".x", "-x", "v1/../x", "v1?x", "v1#x", &"a".repeat(129)] {
+            assert!(validate_tag(tag).is_err(), "{tag}");
+        }
+    }
+
+    #[test]
+    fn test_validate_digest() {
+        for digest in [
+            "sha256:1111111111111111111111111111111111111111111111111111111111111111",
+            "sha512:abcdef",
+            "multihash+base58:abc",
+        ] {
+            assert!(validate_digest(digest).is_ok(), "{digest}");
+        }
+        for digest in [
+            "",
+            "sha256",
+            "sha256:",
+            ":abc",
+            "SHA256:abc",
+            "sha256:ABC",
+            "sha256:abc/../../x",
+            "sha256:abc?x",
+            "sha+:abc",
+        ] {
+            assert!(validate_digest(digest).is_err(), "{digest}");
+        }
+    }
+
+    #[test]
+    fn select_layer_by_index() {
+        let manifest = manifest();
+        assert_eq!(
+            select_layer(&manifest, Some(1), None).unwrap().digest,
+            "sha256:2222222222222222222222222222222222222222222222222222222222222222",
+        );
+        assert_eq!(
+            select_layer(&manifest, Some(2), None)
+                .unwrap_err()
+                .to_string(),
+            "layer 2 requested, but manifest has 2 layers",
+        );
+    }
+
+    #[test]
+    fn select_layer_by_annotation() {
+        let manifest = manifest();
+        let annotation = HashMap::from([(
+            TITLE_ANNOTATION.to_owned(),
+            "tool-linux-x86_64.tar.gz".to_owned(),
+        )]);
+        assert_eq!(
+            select_layer(&manifest, None, Some(&annotation))
+                .unwrap()
+                .digest,
+            "sha256:1111111111111111111111111111111111111111111111111111111111111111",
+        );
+
+        let annotation = HashMap::from([(TITLE_ANNOTATION.to_owned(), "missing".to_owned())]);
+        assert!(select_layer(&manifest, None, Some(&annotation)).is_err());
+    }
+
+    #[test]
+    fn select_layer_requires_selector_for_multiple_layers() {
+        let manifest = manifest();
+        assert_eq!(
+            select_layer(&manifest, None, None).unwrap_err().to_string(),
+            "manifest has 2 layers, so `layer` or `annotation` must be specified \
+             (e.g., `{\"org.opencontainers.image.title\": \"NAME\"}`)",
+        );
+    }
+}
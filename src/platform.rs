@@ -7,6 +7,9 @@
  * of this source tree.
  */
 
+use std::env;
+use std::path::Path;
+
 macro_rules! if_platform {
     (
         linux_aarch64 = $linux_aarch64:tt,
@@ -44,3 +47,208 @@ pub const SUPPORTED_PLATFORM: &str = if_platform! {
     windows_aarch64 = "windows-aarch64",
     windows_x86_64 = "windows-x86_64",
 };
+
+/// The key for artifacts that only depend on the operating system, such as
+/// shell scripts.
+const SUPPORTED_OS: &str = if_platform! {
+    linux_aarch64 = "linux",
+    linux_x86_64 = "linux",
+    macos_aarch64 = "macos",
+    macos_x86_64 = "macos",
+    windows_aarch64 = "windows",
+    windows_x86_64 = "windows",
+};
+
+/// The key for artifacts that run on any platform, such as JARs or wasm
+/// modules.
+const ANY_PLATFORM: &str = "any";
+
+/// The dynamic loaders for musl and glibc, which tell the libc of a Linux
+/// host apart.
+const LINUX_LOADERS: (&str, &str) = if_platform! {
+    linux_aarch64 = ("/lib/ld-musl-aarch64.so.1", "/lib/ld-linux-aarch64.so.1"),
+    linux_x86_64 = ("/lib/ld-musl-x86_64.so.1", "/lib64/ld-linux-x86-64.so.2"),
+    macos_aarch64 = ("", ""),
+    macos_x86_64 = ("", ""),
+    windows_aarch64 = ("", ""),
+    windows_x86_64 = ("", ""),
+};
+
+/// Forces DotSlash to use the entry with this key (e.g., `macos-x86_64`)
+/// instead of the ones it would choose for the host.
+pub const PLATFORM_ENV_VAR: &str = "DOTSLASH_PLATFORM";
+
+/// Returns the keys in `"platforms"` whose artifacts can run on this host,
+/// in order of preference.
+pub fn platform_keys() -> Vec<String> {
+    match env::var(PLATFORM_ENV_VAR) {
+        Ok(platform) if !platform.is_empty() => vec![platform],
+        _ => Host::detect().platform_keys(),
+    }
+}
+
+/// What is known about the host beyond the platform `dotslash` was built
+/// for.
+struct Host {
+    platform: &'static str,
+    os: &'static str,
+    /// Whether an Apple silicon Mac can run x86_64 executables.
+    has_rosetta: bool,
+    /// Whether the libc of a Linux host is musl rather than glibc.
+    is_musl: bool,
+    /// The x86-64 microarchitecture level (1 through 4) of an x86_64 host.
+    x86_64_level: u8,
+}
+
+impl Host {
+    fn detect() -> Host {
+        Host {
+            platform: SUPPORTED_PLATFORM,
+            os: SUPPORTED_OS,
+            has_rosetta: cfg!(all(target_os = "macos", target_arch = "aarch64"))
+                && Path::new("/Library/Apple/usr/libexec/oah/libRosettaRuntime").exists(),
+            is_musl: is_musl(),
+            x86_64_level: x86_64_level(),
+        }
+    }
+
+    fn platform_keys(&self) -> Vec<String> {
+        let mut keys = Vec::new();
+        // A musl build is preferred on a musl host because a glibc build
+        // fails to start there with a confusing ENOENT for its loader.
+        if self.is_musl {
+            keys.push(format!("{}-musl", self.platform));
+        }
+        // Builds for higher microarchitecture levels are preferred, e.g.,
+        // `linux-x86_64-v3` before `linux-x86_64`.
+        if self.platform.ends_with("-x86_64") {
+            for level in (2..=self.x86_64_level).rev() {
+                keys.push(format!("{}-v{}", self.platform, level));
+            }
+        }
+        keys.push(self.platform.to_owned());
+        match self.platform {
+            "macos-aarch64" if self.has_rosetta => keys.push("macos-x86_64".to_owned()),
+            // Windows on Arm emulates x86_64.
+            "windows-aarch64" => keys.push("windows-x86_64".to_owned()),
+            _ => {}
+        }
+        keys.push(self.os.to_owned());
+        keys.push(ANY_PLATFORM.to_owned());
+        keys
+    }
+}
+
+/// Returns the highest x86-64 microarchitecture level whose features the CPU
+/// supports, per the "Micro-architecture levels" table of the x86-64 psABI.
+///
+/// Two requirements cannot be named in `is_x86_feature_detected!`: `LAHF-SAHF`
+/// for x86-64-v2, which every CPU with the other v2 features has, and `OSXSAVE`
+/// for x86-64-v3. The latter is covered because the AVX and AVX-512 features
+/// are only reported when the OS has enabled saving their register state.
+#[cfg(target_arch = "x86_64")]
+fn x86_64_level() -> u8 {
+    macro_rules! has_features {
+        ($($feature:tt),*) => { $(is_x86_feature_detected!($feature))&&* };
+    }
+    if !has_features!("cmpxchg16b", "popcnt", "sse3", "sse4.1", "sse4.2", "ssse3") {
+        1
+    } else if !has_features!("avx", "avx2", "bmi1", "bmi2", "f16c", "fma", "lzcnt", "movbe") {
+        2
+    } else if !has_features!("avx512f", "avx512bw", "avx512cd", "avx512dq", "avx512vl") {
+        3
+    } else {
+        4
+    }
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+fn x86_64_level() -> u8 {
+    1
+}
+
+/// A Linux host is considered to use musl if it has the musl loader but not
+/// the glibc one. Merely having the musl loader is not enough because glibc
+/// distros can install musl alongside glibc.
+fn is_musl() -> bool {
+    let (musl_loader, glibc_loader) = LINUX_LOADERS;
+    cfg!(target_os = "linux")
+        && Path::new(musl_loader).exists()
+        && !Path::new(glibc_loader).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn host(platform: &'static str, os: &'static str) -> Host {
+        Host {
+            platform,
+            os,
+            has_rosetta: false,
+            is_musl: false,
+            x86_64_level: 1,
+        }
+    }
+
+    #[test]
+    fn platform_keys() {
+        assert_eq!(
+            host("linux-x86_64", "linux").platform_keys(),
+            ["linux-x86_64", "linux", "any"],
+        );
+        assert_eq!(
+            host("macos-aarch64", "macos").platform_keys(),
+            ["macos-aarch64", "macos", "any"],
+        );
+        assert_eq!(
+            Host {
+                has_rosetta: true,
+                ..host("macos-aarch64", "macos")
+            }
+            .platform_keys(),
+            ["macos-aarch64", "macos-x86_64", "macos", "any"],
+        );
+        assert_eq!(
+            host("windows-aarch64", "windows").platform_keys(),
+            ["windows-aarch64", "windows-x86_64", "windows", "any"],
+        );
+        assert_eq!(
+            Host {
+                is_musl: true,
+                ..host("linux-aarch64", "linux")
+            }
+            .platform_keys(),
+            ["linux-aarch64-musl", "linux-aarch64", "linux", "any"],
+        );
+        assert_eq!(
+            Host {
+                x86_64_level: 3,
+                ..host("linux-x86_64", "linux")
+            }
+            .platform_keys(),
+            [
+                "linux-x86_64-v3",
+                "linux-x86_64-v2",
+                "linux-x86_64",
+                "linux",
+                "any"
+            ],
+        );
+        assert_eq!(
+            Host {
+                x86_64_level: 2,
+                is_musl: true,
+                ..host("linux-x86_64", "linux")
+            }
+            .platform_keys(),
+            [
+                "linux-x86_64-musl",
+                "linux-x86_64-v2",
+                "linux-x86_64",
+                "linux",
+                "any"
+            ],
+        );
+    }
+}
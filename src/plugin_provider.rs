@@ -0,0 +1,194 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Providers implemented by external executables.
+//!
+//! A provider of type `TYPE` that DotSlash does not know about is handled by
+//! an executable named `dotslash-provider-TYPE`, which is looked up in the
+//! directories in `$DOTSLASH_PLUGIN_DIR` and then on `$PATH`. The plugin is
+//! sent a JSON request on stdin:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "provider": { "type": "TYPE", ... },
+//!   "destination": "/path/to/write/the/artifact/to",
+//!   "size": 123,
+//!   "hash": "sha256",
+//!   "digest": "...",
+//!   "dotslash_file": "/path/to/the/dotslash/file"
+//! }
+//! ```
+//!
+//! and must write the artifact to `destination`, then print a JSON result to
+//! stdout: either `{"status": "ok"}` or
+//! `{"status": "error", "message": "..."}`.
+
+use std::env;
+use std::ffi::OsString;
+use std::io::Write as _;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+use std::process::Stdio;
+
+use anyhow::format_err;
+use anyhow::Context as _;
+use serde::Deserialize;
+use serde_jsonrc::json;
+use serde_jsonrc::value::Value;
+
+use crate::config::ArtifactEntry;
+use crate::provider::Provider;
+use crate::util::display::CommandDisplay;
+use crate::util::display::CommandStderrDisplay;
+use crate::util::file_lock::FileLock;
+
+/// Directories (separated like `$PATH`) to search for plugins before `$PATH`.
+pub const PLUGIN_DIR_ENV_VAR: &str = "DOTSLASH_PLUGIN_DIR";
+
+const PLUGIN_PREFIX: &str = "dotslash-provider-";
+
+/// Version of the request sent to plugins.
+const PROTOCOL_VERSION: u32 = 1;
+
+pub struct PluginProvider {
+    executable: PathBuf,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(tag = "status", rename_all = "lowercase")]
+enum PluginResult {
+    Ok,
+    Error { message: String },
+}
+
+impl PluginProvider {
+    /// Returns the provider for `provider_type` if a plugin for it is found.
+    pub fn find(provider_type: &str) -> Option<PluginProvider> {
+        // The type becomes part of a file name, so keep it to characters that
+        // cannot escape the directory being searched.
+        let is_valid_type = !provider_type.is_empty()
+            && provider_type
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+        if !is_valid_type {
+            return None;
+        }
+
+        let file_name = format!(
+            "{}{}{}",
+            PLUGIN_PREFIX,
+            provider_type,
+            env::consts::EXE_SUFFIX
+        );
+        let search_path = [PLUGIN_DIR_ENV_VAR, "PATH"]
+            .into_iter()
+            .filter_map(env::var_os)
+            .collect::<Vec<OsString>>();
+        search_path
+            .iter()
+            .flat_map(env::split_paths)
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .map(|dir| dir.join(&file_name))
+            .find(|path| path.is_file())
+            .map(|executable| PluginProvider { executable })
+    }
+}
+
+impl Provider for PluginProvider {
+    fn fetch_artifact(
+        &self,
+        provider_config: &Value,
+        destination: &Path,
+        _fetch_lock: &FileLock,
+        artifact_entry: &ArtifactEntry,
+        dotslash_file: &Path,
+    ) -> anyhow::Result<()> {
+        let request = json!({
+            "version": PROTOCOL_VERSION,
+            "provider": provider_config,
+            "destination": destination,
+            "size": artifact_entry.size,
+            "hash": artifact_entry.hash,
+            "digest": artifact_entry.digest,
+            "dotslash_file": dotslash_file,
+        });
+
+        let mut command = Command::new(&self.executable);
+        command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let mut child = command
+            .spawn()
+            .with_context(|| format!("failed to execute `{}`", CommandDisplay::new(&command)))?;
+        let mut stdin = child.stdin.take().unwrap();
+        // If the plugin exits without reading its input, the error is
+        // reported based on its exit status and output instead.
+        let _ = serde_jsonrc::to_writer(&mut stdin, &request);
+        let _ = stdin.flush();
+        drop(stdin);
+        let output = child
+            .wait_with_output()
+            .with_context(|| format!("failed to execute `{}`", CommandDisplay::new(&command)))?;
+
+        if !output.status.success() {
+            return Err(format_err!("{}", CommandStderrDisplay::new(&output)))
+                .with_context(|| format!("`{}`", CommandDisplay::new(&command)));
+        }
+
+        match parse_result(&output.stdout) {
+            Ok(PluginResult::Ok) => Ok(()),
+            Ok(PluginResult::Error { message }) => Err(format_err!(
+                "plugin `{}` failed: {}",
+                self.executable.display(),
+                message,
+            )),
+            Err(e) => Err(e).with_context(|| {
+                format!(
+                    "failed to parse result from plugin `{}`",
+                    self.executable.display(),
+                )
+            }),
+        }
+    }
+}
+
+fn parse_result(stdout: &[u8]) -> serde_jsonrc::Result<PluginResult> {
+    serde_jsonrc::from_slice(stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_result() {
+        assert_eq!(
+            parse_result(br#"{"status": "ok"}"#).unwrap(),
+            PluginResult::Ok
+        );
+        assert_eq!(
+            parse_result(b"{\"status\": \"error\", \"message\": \"no ticket\"}\n").unwrap(),
+            PluginResult::Error {
+                message: "no ticket".to_owned()
+            },
+        );
+        assert!(parse_result(b"").is_err());
+        assert!(parse_result(br#"{"status": "maybe"}"#).is_err());
+    }
+
+    #[test]
+    fn find_rejects_types_that_are_not_file_names() {
+        assert!(PluginProvider::find("").is_none());
+        assert!(PluginProvider::find("../../bin/sh").is_none());
+        assert!(PluginProvider::find("a/b").is_none());
+    }
+}
@@ -7,6 +7,7 @@
  * of this source tree.
  */
 
+use std::collections::BTreeMap;
 use std::ffi::OsStr;
 use std::fs::File;
 use std::io::BufReader;
@@ -20,16 +21,15 @@ use sha2::Digest as _;
 use crate::artifact_path::ArtifactPath;
 use crate::config::ArtifactEntry;
 use crate::config::HashAlgorithm;
-use crate::curl::CurlCommand;
-use crate::curl::FetchContext;
 use crate::fetch_method::ArtifactFormat;
+use crate::http_client::FetchContext;
+use crate::http_client::HttpRequest;
 
 type LooseArtifactEntry = ArtifactEntry<String>;
 
 /// This function creates an approximate ArtifactEntry for the specified URL
 /// and writes it to stdout as pretty-printed JSON.
 pub(crate) fn print_entry_for_url(url: &OsStr) -> anyhow::Result<()> {
-    let curl_cmd = CurlCommand::new(url);
     let url = url
         .to_str()
         .with_context(|| format!("arg is not UTF-8 `{}`", url.to_string_lossy()))?;
@@ -39,7 +39,7 @@ pub(crate) fn print_entry_for_url(url: &OsStr) -> anyhow::Result<()> {
         show_progress: std::io::stderr().is_terminal(),
     };
     let tempfile = tempfile::NamedTempFile::new()?;
-    curl_cmd
+    HttpRequest::new(url)
         .get_request(tempfile.path(), &fetch_context)
         .with_context(|| format!("failed to fetch `{}`", url))?;
 
@@ -74,6 +74,12 @@ fn serialize_entry(url: &str, size: u64, hex_digest: impl Into<String>) -> anyho
         path: ArtifactPath::from_str("TODO: specify the appropriate `path` for this artifact")?,
         providers: vec![json!({"url": url})],
         readonly: true,
+        zstd: None,
+        extract: vec![],
+        strip_components: 0,
+        executables: vec![],
+        symlinks: BTreeMap::new(),
+        interpreter: None,
     };
     let entry_json = serde_jsonrc::to_string_pretty(&entry)?;
     Ok(entry_json)
@@ -84,10 +90,24 @@ fn guess_artifact_format_from_url(url: &[u8]) -> ArtifactFormat {
         ArtifactFormat::TarGz
     } else if url.ends_with(b".tar.zst") || url.ends_with(b".tzst") {
         ArtifactFormat::TarZstd
+    } else if url.ends_with(b".tar.xz") || url.ends_with(b".txz") {
+        ArtifactFormat::TarXz
+    } else if url.ends_with(b".tar.bz2") || url.ends_with(b".tbz2") || url.ends_with(b".tbz") {
+        ArtifactFormat::TarBz2
     } else if url.ends_with(b".tar") {
         ArtifactFormat::Tar
+    } else if url.ends_with(b".deb") {
+        ArtifactFormat::Deb
+    } else if url.ends_with(b".rpm") {
+        ArtifactFormat::Rpm
+    } else if url.ends_with(b".zip") {
+        ArtifactFormat::Zip
     } else if url.ends_with(b".gz") {
         ArtifactFormat::Gz
+    } else if url.ends_with(b".xz") {
+        ArtifactFormat::Xz
+    } else if url.ends_with(b".bz2") {
+        ArtifactFormat::Bz2
     } else if url.ends_with(b".zst") {
         ArtifactFormat::Zstd
     } else {
@@ -122,6 +142,12 @@ mod tests {
                 .unwrap(),
                 providers: vec![json!({"url": url})],
                 readonly: true,
+                zstd: None,
+                extract: vec![],
+                strip_components: 0,
+                executables: vec![],
+                symlinks: BTreeMap::new(),
+                interpreter: None,
             },
             entry,
         );
@@ -170,9 +196,19 @@ mod tests {
         test("http://example.com/foo.tgz", ArtifactFormat::TarGz);
         test("http://example.com/foo.tar.zst", ArtifactFormat::TarZstd);
         test("http://example.com/foo.tzst", ArtifactFormat::TarZstd);
+        test("http://example.com/foo.tar.xz", ArtifactFormat::TarXz);
+        test("http://example.com/foo.txz", ArtifactFormat::TarXz);
+        test("http://example.com/foo.tar.bz2", ArtifactFormat::TarBz2);
+        test("http://example.com/foo.tbz2", ArtifactFormat::TarBz2);
+        test("http://example.com/foo.tbz", ArtifactFormat::TarBz2);
         test("http://example.com/foo.tar", ArtifactFormat::Tar);
         test("http://example.com/foo.gz", ArtifactFormat::Gz);
         test("http://example.com/foo.zst", ArtifactFormat::Zstd);
+        test("http://example.com/foo.xz", ArtifactFormat::Xz);
+        test("http://example.com/foo.bz2", ArtifactFormat::Bz2);
+        test("http://example.com/foo.zip", ArtifactFormat::Zip);
+        test("http://example.com/foo_1.0_amd64.deb", ArtifactFormat::Deb);
+        test("http://example.com/foo-1.0.x86_64.rpm", ArtifactFormat::Rpm);
 
         // These "backwards" extensions are interpreted as Tar.
         test("http://example.com/foo.zst.tar", ArtifactFormat::Tar);
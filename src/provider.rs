@@ -31,13 +31,19 @@ pub trait Provider {
     ///     information in the entry to perform the fetch, as such information
     ///     should be defined in the provider_config. It is primarily provided
     ///     so the Provider can show an appropriate progess indicator based on
-    ///     the expected size of the artifact.
+    ///     the expected size of the artifact. (A content-addressed provider is
+    ///     the exception, as it locates the artifact by its digest.)
+    ///
+    /// dotslash_file: Path to the DotSlash file that is being run. Providers
+    ///     that accept relative paths should resolve them against the
+    ///     directory that contains this file.
     fn fetch_artifact(
         &self,
         provider_config: &Value,
         destination: &Path,
         fetch_lock: &FileLock,
         artifact_entry: &ArtifactEntry,
+        dotslash_file: &Path,
     ) -> anyhow::Result<()>;
 }
 
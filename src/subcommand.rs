@@ -22,7 +22,7 @@ use thiserror::Error;
 use crate::config::parse_file;
 use crate::config::REQUIRED_HEADER;
 use crate::dotslash_cache::DotslashCache;
-use crate::platform::SUPPORTED_PLATFORM;
+use crate::platform;
 use crate::print_entry_for_url::print_entry_for_url;
 use crate::util::fs_ctx;
 
@@ -48,6 +48,10 @@ pub enum Subcommand {
     /// and prints only the hash
     Sha256,
 
+    /// Parse a DotSlash file and check the entries for all platforms, not just
+    /// the current one
+    Validate,
+
     /// Version
     Version,
 
@@ -64,6 +68,7 @@ impl fmt::Display for Subcommand {
             Self::CacheDir => "cache-dir",
             Self::Parse => "parse",
             Self::Sha256 => "sha256",
+            Self::Validate => "validate",
             Self::Version => "version",
             Self::Help => "help",
         })
@@ -81,6 +86,7 @@ impl FromStr for Subcommand {
             "cache-dir" => Ok(Subcommand::CacheDir),
             "parse" => Ok(Subcommand::Parse),
             "sha256" => Ok(Subcommand::Sha256),
+            "validate" => Ok(Subcommand::Validate),
             "version" => Ok(Subcommand::Version),
             "help" => Ok(Subcommand::Help),
             _ => Err(SubcommandError::UnknownCommand(name.to_owned())),
@@ -159,6 +165,14 @@ fn _run_subcommand(subcommand: &Subcommand, args: &mut ArgsOs) -> anyhow::Result
             println!("{json}");
         }
 
+        Subcommand::Validate => {
+            let file_arg = take_exactly_one_arg(args)?;
+            let dotslash_data = fs_ctx::read_to_string(file_arg)?;
+            let (_original_json, config_file) =
+                parse_file(&dotslash_data).context("failed to parse file")?;
+            config_file.validate()?;
+        }
+
         Subcommand::Version => {
             if args.next().is_some() {
                 return Err(format_err!("expected no arguments but received some"));
@@ -182,14 +196,14 @@ that DOTSLASH_FILE represents.
 All OPTIONS will be forwarded directly to the executable identified by
 DOTSLASH_FILE.
 
-Supported platform: {}
+Supported platforms: {}
 
 Your DotSlash cache is: {}
 
 Learn more at {}
 "##,
                 REQUIRED_HEADER,
-                SUPPORTED_PLATFORM,
+                platform::platform_keys().join(", "),
                 DotslashCache::new().cache_dir().display(),
                 env!("CARGO_PKG_HOMEPAGE"),
             );
@@ -0,0 +1,183 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Rules that rewrite the URL of every HTTP request that providers make, so
+//! that DotSlash files that point at public hosts can be served from a mirror.
+
+use std::env;
+use std::io;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use thiserror::Error;
+
+/// Path to a rules file to use instead of the user and machine rules files.
+pub const URL_REWRITES_ENV_VAR: &str = "DOTSLASH_URL_REWRITES";
+
+const RULES_FILE_NAME: &str = "url-rewrites.json";
+
+#[derive(Deserialize, Debug, Default, PartialEq)]
+pub struct UrlRewrites {
+    #[serde(default)]
+    rewrites: Vec<UrlRewrite>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+struct UrlRewrite {
+    prefix: String,
+    replacement: String,
+    /// Also try the original URL if the rewritten one fails.
+    #[serde(default)]
+    keep_original: bool,
+}
+
+#[derive(Debug, Error)]
+pub enum UrlRewriteError {
+    #[error("failed to read URL rewrite rules `{0}`")]
+    Read(PathBuf, #[source] io::Error),
+
+    #[error("failed to parse URL rewrite rules `{0}`")]
+    Parse(PathBuf, #[source] serde_jsonrc::Error),
+}
+
+impl UrlRewrites {
+    /// Reads the rules file named by `$DOTSLASH_URL_REWRITES` or, if unset,
+    /// the user's rules file followed by the machine's rules file. Missing
+    /// files have no rules.
+    pub fn load() -> Result<UrlRewrites, UrlRewriteError> {
+        let paths = match env::var_os(URL_REWRITES_ENV_VAR) {
+            Some(path) => vec![PathBuf::from(path)],
+            None => default_paths(),
+        };
+
+        let mut rules = UrlRewrites::default();
+        for path in paths {
+            let contents = match std::fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(UrlRewriteError::Read(path, e)),
+            };
+            let file_rules = match serde_jsonrc::from_str::<UrlRewrites>(&contents) {
+                Ok(file_rules) => file_rules,
+                Err(e) => return Err(UrlRewriteError::Parse(path, e)),
+            };
+            rules.rewrites.extend(file_rules.rewrites);
+        }
+        Ok(rules)
+    }
+
+    /// Whether any rule rewrites `url`.
+    pub fn matches(&self, url: &str) -> bool {
+        self.rewrites
+            .iter()
+            .any(|rule| url.starts_with(&rule.prefix))
+    }
+
+    /// Returns the URLs to request instead of `url`, in order. If `url`
+    /// matches a rule, it is rewritten, and it is also tried as is afterwards
+    /// if the rule has `keep_original`. When several rules match, the one with
+    /// the longest prefix wins.
+    pub fn apply(&self, url: &str) -> Vec<String> {
+        let rule = self
+            .rewrites
+            .iter()
+            .filter(|rule| url.starts_with(&rule.prefix))
+            .max_by_key(|rule| rule.prefix.len());
+        match rule {
+            Some(rule) => {
+                let rewritten = format!("{}{}", rule.replacement, &url[rule.prefix.len()..]);
+                if rule.keep_original {
+                    vec![rewritten, url.to_owned()]
+                } else {
+                    vec![rewritten]
+                }
+            }
+            None => vec![url.to_owned()],
+        }
+    }
+}
+
+fn default_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(config_dir) = dirs::config_dir() {
+        paths.push(config_dir.join("dotslash").join(RULES_FILE_NAME));
+    }
+    if cfg!(windows) {
+        if let Some(program_data) = env::var_os("ProgramData") {
+            paths.push(
+                PathBuf::from(program_data)
+                    .join("dotslash")
+                    .join(RULES_FILE_NAME),
+            );
+        }
+    } else {
+        paths.push(PathBuf::from("/etc/dotslash").join(RULES_FILE_NAME));
+    }
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> UrlRewrites {
+        serde_jsonrc::from_str(
+            r#"{
+                // Comments are allowed, as in DotSlash files.
+                "rewrites": [
+                    {
+                        "prefix": "https://github.com/",
+                        "replacement": "https://mirror.corp/github/",
+                    },
+                    {
+                        "prefix": "https://github.com/facebook/",
+                        "replacement": "https://mirror.corp/facebook/",
+                        "keep_original": true,
+                    },
+                ],
+            }"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn apply_rewrites_matching_urls() {
+        assert_eq!(
+            rules().apply("https://github.com/zertosh/dotslash_fixtures/raw/pack.tar.gz"),
+            ["https://mirror.corp/github/zertosh/dotslash_fixtures/raw/pack.tar.gz"],
+        );
+        assert_eq!(
+            rules().apply("https://example.com/pack.tar.gz"),
+            ["https://example.com/pack.tar.gz"],
+        );
+        assert!(rules().matches("https://github.com/facebook/hermes"));
+        assert!(!rules().matches("https://api.github.com/repos/facebook/hermes"));
+    }
+
+    #[test]
+    fn apply_uses_longest_prefix_and_keeps_original() {
+        assert_eq!(
+            rules().apply(
+                "https://github.com/facebook/hermes/releases/download/v0.12.0/hermes.tar.gz"
+            ),
+            [
+                "https://mirror.corp/facebook/hermes/releases/download/v0.12.0/hermes.tar.gz",
+                "https://github.com/facebook/hermes/releases/download/v0.12.0/hermes.tar.gz",
+            ],
+        );
+    }
+
+    #[test]
+    fn no_rules() {
+        assert_eq!(
+            UrlRewrites::default().apply("https://github.com/pack.tar.gz"),
+            ["https://github.com/pack.tar.gz"],
+        );
+    }
+}
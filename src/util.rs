@@ -14,5 +14,7 @@ pub mod execv;
 pub mod file_lock;
 pub mod fs_ctx;
 pub mod http_status;
+pub mod interpolate_env;
 pub mod make_tree_read_only;
 pub mod mv_no_clobber;
+pub mod percent_encode;
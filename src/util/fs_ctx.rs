@@ -78,7 +78,6 @@ pub fn canonicalize<P: AsRef<Path>>(path: P) -> io::Result<PathBuf> {
     fs::canonicalize(&path).map_err(|source| wrap1(source, "canonicalize", path))
 }
 
-#[allow(dead_code)]
 pub fn copy<P: AsRef<Path>, Q: AsRef<Path>>(from: P, to: Q) -> io::Result<u64> {
     fs::copy(&from, &to).map_err(|source| wrap2(source, "copy from", from, "to", to))
 }
@@ -0,0 +1,110 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Substitution of `${env:NAME}` references in values from a DotSlash file.
+
+use std::env;
+
+use thiserror::Error;
+
+const PREFIX: &str = "${env:";
+
+/// The errors only name the variable, never a value, because the result of
+/// interpolation is typically a secret.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum InterpolateEnvError {
+    #[error("environment variable `{0}` is not set")]
+    NotSet(String),
+
+    #[error("unterminated `${{env:` reference")]
+    Unterminated,
+
+    #[error("invalid environment variable name `{0}`")]
+    InvalidName(String),
+}
+
+/// Replaces every `${env:NAME}` in `s` with the value of the environment
+/// variable `NAME`. Anything else, including a `$` that does not start a
+/// reference, is copied as-is.
+pub fn interpolate_env(s: &str) -> Result<String, InterpolateEnvError> {
+    interpolate_with(s, |name| env::var(name).ok())
+}
+
+fn interpolate_with(
+    s: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String, InterpolateEnvError> {
+    let mut output = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find(PREFIX) {
+        output.push_str(&rest[..start]);
+        let after_prefix = &rest[start + PREFIX.len()..];
+        let end = after_prefix
+            .find('}')
+            .ok_or(InterpolateEnvError::Unterminated)?;
+        let name = &after_prefix[..end];
+        if name.is_empty() || !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_') {
+            return Err(InterpolateEnvError::InvalidName(name.to_owned()));
+        }
+        let value = lookup(name).ok_or_else(|| InterpolateEnvError::NotSet(name.to_owned()))?;
+        output.push_str(&value);
+        rest = &after_prefix[end + 1..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "TOKEN" => Some("s3cr3t".to_owned()),
+            "USER" => Some("dotslash".to_owned()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn interpolate() {
+        assert_eq!(
+            interpolate_with("Bearer ${env:TOKEN}", lookup),
+            Ok("Bearer s3cr3t".to_owned()),
+        );
+        assert_eq!(
+            interpolate_with("${env:USER}:${env:TOKEN}", lookup),
+            Ok("dotslash:s3cr3t".to_owned()),
+        );
+        assert_eq!(
+            interpolate_with("no references, $5 or ${TOKEN}", lookup),
+            Ok("no references, $5 or ${TOKEN}".to_owned()),
+        );
+    }
+
+    #[test]
+    fn interpolate_errors() {
+        assert_eq!(
+            interpolate_with("Bearer ${env:MISSING}", lookup),
+            Err(InterpolateEnvError::NotSet("MISSING".to_owned())),
+        );
+        assert_eq!(
+            interpolate_with("Bearer ${env:TOKEN", lookup),
+            Err(InterpolateEnvError::Unterminated),
+        );
+        assert_eq!(
+            interpolate_with("Bearer ${env:TO-KEN}", lookup),
+            Err(InterpolateEnvError::InvalidName("TO-KEN".to_owned())),
+        );
+        assert_eq!(
+            InterpolateEnvError::Unterminated.to_string(),
+            "unterminated `${env:` reference",
+        );
+    }
+}
@@ -0,0 +1,47 @@
+/*
+ * Copyright (c) Meta Platforms, Inc. and affiliates.
+ *
+ * This source code is licensed under both the MIT license found in the
+ * LICENSE-MIT file in the root directory of this source tree and the Apache
+ * License, Version 2.0 found in the LICENSE-APACHE file in the root directory
+ * of this source tree.
+ */
+
+//! Percent-encoding for building URLs from values in a DotSlash file.
+
+use std::fmt::Write as _;
+
+/// Percent-encodes everything except the unreserved characters from
+/// RFC 3986. If `encode_slash` is false, `/` is also left as-is so that a
+/// value can be used as a multi-segment path.
+pub fn percent_encode(s: &str, encode_slash: bool) -> String {
+    s.bytes()
+        .fold(String::with_capacity(s.len()), |mut output, b| {
+            match b {
+                b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                    output.push(b as char)
+                }
+                b'/' if !encode_slash => output.push('/'),
+                _ => {
+                    let _ = write!(output, "%{b:02X}");
+                }
+            }
+            output
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_percent_encode() {
+        assert_eq!(
+            percent_encode("tools/my_tool-1.0.tar.gz", false),
+            "tools/my_tool-1.0.tar.gz"
+        );
+        assert_eq!(percent_encode("a b/c+d=e", false), "a%20b/c%2Bd%3De");
+        assert_eq!(percent_encode("a/b", true), "a%2Fb");
+        assert_eq!(percent_encode("caf\u{e9}~", false), "caf%C3%A9~");
+    }
+}